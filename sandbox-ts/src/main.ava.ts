@@ -35,20 +35,25 @@ test.afterEach.always(async (t) => {
 test("mentor create full course & student enrolled it after paying course price to mentor", async (t) => {
   const { contract, ayoub, ahmed } = t.context.accounts;
 
+  // every account pays for the storage it uses, deposit for it first
+  for (const account of [contract, ayoub, ahmed]) {
+    await account.call(
+      contract,
+      "storage_deposit",
+      {},
+      { attachedDeposit: toYocto("1") }
+    );
+  }
+
   // craete mentor
   const mentorSucess = await ayoub.call(contract, "create_user", {
     name: "Ayoub",
     username: "ayoub",
-    role: "mentor",
+    phone: "0600000000",
     email: "ayoub@gmail.com",
-    password: "123",
     by_google: false,
-    bio: "I am a software engineer",
-    skills: ["JavaScript", "TypeScript", "React", "Node.js"],
-    certifications: ["AWS", "Google Cloud", "Docker"],
-    education: ["Bachelor's degree in Computer Science"],
     picture: "https://avatars.githubusercontent.com/u/47231147?v=4",
-    created_at: new Date().getTime(),
+    client_created_at: new Date().getTime(),
   });
 
   t.is(mentorSucess, true);
@@ -57,35 +62,42 @@ test("mentor create full course & student enrolled it after paying course price
 
   console.log(mentor);
 
-  // mentor create course
-  const courseSucess = await ayoub.call(contract, "create_course", {
+  // the contract owner lets ayoub create courses
+  await contract.call(contract, "grant_role", {
+    account_id: ayoub.accountId,
+    role: "mentor",
+  });
+
+  // mentor create course, it is returned once created
+  const createdCourse: any = await ayoub.call(contract, "create_course", {
     title: "React From Scratch",
     description: "React course",
     level: "beginner",
     duration: "1 month",
     category: "web development",
-    with_ai: false,
-    tags: ["React", "JavaScript", "Frontend"],
-    price: 6,
+    requirements: ["JavaScript"],
+    objectives: ["React", "Frontend"],
     picture: "https://avatars.githubusercontent.com/u/47231147?v=4",
-    created_at: new Date().getTime(),
+    with_ai: false,
+    price: toYocto("6"),
+    client_created_at: new Date().getTime(),
   });
 
   const course = await contract.view("get_course_by_id", { course_id: 0 });
 
   console.log("Course : ", course);
 
-  t.is(courseSucess, true);
+  t.is(createdCourse.id, 0);
 
   // mentor create a module for the course
   const moduleSucess = await ayoub.call(contract, "create_module", {
     course_id: 0,
     title: "React Basics",
     description: "React basics",
-    status: "created",
+    status: "draft",
     order: 1,
     with_ai: false,
-    created_at: new Date().getTime(),
+    client_created_at: new Date().getTime(),
   });
 
   const module = await contract.view("get_module_by_id", { module_id: 0 });
@@ -104,7 +116,7 @@ test("mentor create full course & student enrolled it after paying course price
     article:
       "shhhhhhhhhhhhhhhhhhhhhhhhhhhhhhhdjhssgfgsfqlslsfihfsdbfshsffsjfsfsbfldidvqsgsvlkfolgyvcvsdmlqùsmhfbgfjfjdfsmsfyfbtbu",
     with_ai: false,
-    created_at: new Date().getTime(),
+    client_created_at: new Date().getTime(),
   });
 
   const lesson = await contract.view("get_lesson_by_id", { lesson_id: 0 });
@@ -121,7 +133,6 @@ test("mentor create full course & student enrolled it after paying course price
   // publish the course
   const publishSucess = await ayoub.call(contract, "publish_course", {
     course_id: 0,
-    published_at: new Date().getTime(),
   });
 
   t.is(publishSucess, true);
@@ -130,34 +141,36 @@ test("mentor create full course & student enrolled it after paying course price
   const studentSucess = await ahmed.call(contract, "create_user", {
     name: "Ahmed",
     username: "ahmed",
-    role: "student",
+    phone: "0600000001",
     email: "ahmed@gmail.com",
-    password: "123",
     by_google: false,
-    bio: "I am a student",
-    skills: ["JavaScript", "TypeScript", "React", "Node.js"],
-    certifications: ["AWS", "Google Cloud", "Docker"],
-    education: ["Bachelor's degree in Computer Science"],
     picture: "https://avatars.githubusercontent.com/u/47231147?v=4",
-    created_at: new Date().getTime(),
+    client_created_at: new Date().getTime(),
   });
 
   t.is(studentSucess, true);
 
-  // student enroll in the course
-  const enrollSucess = await ahmed.call(
+  // student add the course to the cart
+  const cartSucess = await ahmed.call(contract, "save_course_to_cart", {
+    course_id: 0,
+  });
+
+  t.is(cartSucess, true);
+
+  // student enroll in the carted courses, paying the price plus the 10% fee
+  // and getting the rest back
+  const receipt: any = await ahmed.call(
     contract,
-    "enroll_course",
-    {
-      course_id: 0,
-      enrolled_at: new Date().getTime(),
-    },
+    "enroll_all_carted_courses",
+    {},
     {
       attachedDeposit: toYocto("7"),
     }
   );
 
-  t.is(enrollSucess, true);
+  t.is(receipt.courses.length, 1);
+  t.is(receipt.total_price, toYocto("6.6"));
+  t.is(receipt.refund, toYocto("0.4"));
 
   // get student enrolled courses
   const studentCourses = await contract.view("get_student_courses", {
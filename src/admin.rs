@@ -1,3 +1,5 @@
+use crate::errors::ContractError;
+use crate::events::*;
 use crate::models::*;
 use crate::{Contract, ContractExt};
 use near_sdk::env::log_str;
@@ -6,57 +8,87 @@ use near_sdk::{env, near_bindgen, AccountId};
 
 #[near_bindgen]
 impl Contract {
-    pub fn change_user_to_admin(&mut self, account_id: AccountId) {
-        // check if caller is admin
-        let account_id = env::signer_account_id();
-
-        // check if the caller account is ayoubamer.testnet
-        assert!(
-            account_id.to_string() == "ayoubamer.testnet",
-            "Only admin can set admins"
-        );
+    #[handle_result]
+    pub fn change_user_to_admin(&mut self, account_id: AccountId) -> Result<(), ContractError> {
+        // only the owner can set admins
+        let owner_id = self.require_role(Role::Owner, "Only the owner can set admins")?;
+        let initial_storage = env::storage_usage();
 
         // check if the user exists
         let user = self.get_user_by_id(account_id.clone());
         if user.is_none() {
-            log_str("User does not exist");
-            env::panic_str("User does not exist")
+            return Err(ContractError::NotFound("User does not exist".to_string()));
         }
 
-        let mut user = user.unwrap();
-        user.role = "admin".to_string();
+        // grant the admin role in the role registry
+        self.internal_grant_role(account_id.clone(), Role::Admin);
 
-        // getting the user index in the users vector
-        let user_index = self
-            .users
-            .iter()
-            .position(|u| u.account_id == user.account_id)
-            .unwrap();
+        let mut user = user.unwrap();
+        user.role = UserRole::Admin;
+        user.updated_at = env::block_timestamp();
 
         // update the user
-        self.users.replace(user_index as u32, user.clone());
+        self.internal_save_user(user.clone());
 
         log_str(&format!("User {} is now an admin", account_id));
+
+        // emit the user_updated event
+        CourchainEvent::UserUpdated(vec![UserUpdatedData {
+            account_id: user.account_id.clone(),
+            username: user.username.clone(),
+            name: user.name.clone(),
+            role: user.role,
+            updated_by: owner_id.clone(),
+            updated_at: user.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        Ok(())
     }
 
-    pub fn update_course_price_by_admin(&mut self, course_id: u64, price: U128) {
+    #[handle_result]
+    pub fn update_course_price_by_admin(
+        &mut self,
+        course_id: u64,
+        price: U128,
+    ) -> Result<(), ContractError> {
         // check if caller is admin
-        let account_id = env::signer_account_id();
+        let account_id = self.require_role(Role::Admin, "Only admin can update course price")?;
+        let initial_storage = env::storage_usage();
 
-        // check if the caller account is ayoubamer.testnet
-        assert!(
-            account_id.to_string() == "ayoubamer.testnet",
-            "Only admin can update course price"
-        );
-
-        let mut course = self.get_course_by_id(course_id.clone()).unwrap();
         // check if course exists
-        assert!(course.id == course_id, "Course does not exist");
+        let course = self.get_course_by_id(course_id);
+        if course.is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+        let mut course = course.unwrap();
         course.price = u128::from(price);
+        course.updated_at = env::block_timestamp();
 
-        self.courses.set(course_id as u32, course);
+        // update the course
+        self.courses.insert(course_id, course.clone());
+
+        // emit the course_updated event
+        CourchainEvent::CourseUpdated(vec![CourseUpdatedData {
+            course_id,
+            title: course.title.clone(),
+            price,
+            category: course.category.clone(),
+            updated_by: account_id.clone(),
+            updated_at: course.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
     }
 
+    #[handle_result]
     pub fn save_course_by_admin(
         &mut self,
         mentor_id: AccountId,
@@ -70,31 +102,34 @@ impl Contract {
         picture: String,
         with_ai: bool,
         price: u128,
-        created_at: u64,
-    ) -> u64 {
-        let account_id: AccountId = env::signer_account_id();
-
-        // only ayoubamer.testnet can save courses
-        if account_id.to_string() != "ayoubamer.testnet".to_string() {
-            // return Err("Only the admin can save courses".to_string());
-            env::panic_str("Only the admin can save courses")
-        }
+        client_created_at: Option<u64>,
+    ) -> Result<u64, ContractError> {
+        // only admins can save courses
+        let account_id = self.require_role(Role::Admin, "Only the admin can save courses")?;
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the mentor exists
-        // let mentor = self.get_user_by_id(mentor_id.clone());
-        // if mentor.is_none() {
-        //     log_str("Mentor does not exist");
-        //     env::panic_str("Mentor does not exist")
-        // }
+        let mentor = self.get_user_by_id(mentor_id.clone());
+        if mentor.is_none() {
+            return Err(ContractError::NotFound("Mentor does not exist".to_string()));
+        }
+
+        // the mentor could not manage the course without the mentor role
+        if !self.has_role(mentor_id.clone(), Role::Mentor) {
+            return Err(ContractError::InvalidInput(
+                "Account does not have the mentor role".to_string(),
+            ));
+        }
 
-        log_str(&format!("Creating New Course..."));
+        log_str("Creating New Course...");
         let new_course: Course = Course {
             id: self.course_count,
             title: title.clone(),
             description,
             level,
             duration,
-            status: "draft".to_string(),
+            status: CourseStatus::Draft,
             category,
             requirements,
             objectives,
@@ -103,12 +138,22 @@ impl Contract {
             price,
             created_at,
             updated_at: created_at,
+            client_created_at,
             mentor_id: mentor_id.clone(),
             modules_ids: vec![],
+            rating_count: 0,
+            rating_sum: 0,
+            average_rating: 0.0,
         };
 
-        // add course to the courses vector
-        self.courses.push(new_course.clone());
+        // add course to the courses map
+        self.courses.insert(new_course.id, new_course.clone());
+
+        // index the course under its mentor
+        self.mentor_courses
+            .entry(mentor_id.clone())
+            .or_default()
+            .push(new_course.id);
 
         // Increment the course count
         self.course_count += 1;
@@ -116,34 +161,46 @@ impl Contract {
         // Log the creation of the course
         log_str(&format!("Course created: {} by {}", title, mentor_id));
 
-        return new_course.id;
+        // emit the course_created event
+        CourchainEvent::CourseCreated(vec![CourseCreatedData {
+            course_id: new_course.id,
+            mentor_id,
+            title,
+            price: U128(price),
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(new_course.id)
     }
 
+    #[handle_result]
     pub fn save_module_by_admin(
         &mut self,
         course_id: u64,
         title: String,
         description: String,
-        status: String,
+        status: ModuleStatus,
         order: u64,
         with_ai: bool,
-        created_at: u64,
-    ) -> Module {
-        let account_id: AccountId = env::signer_account_id();
-
-        // only ayoubamer.testnet can save modules
-        if account_id.to_string() != "ayoubamer.testnet".to_string() {
-            env::panic_str("Only the admin can save modules")
-        }
+        client_created_at: Option<u64>,
+    ) -> Result<Module, ContractError> {
+        // only admins can save modules
+        let account_id = self.require_role(Role::Admin, "Only the admin can save modules")?;
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the course exists
         let course = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            env::panic_str("Course does not exist")
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
-        log_str(&format!("Creating New Module..."));
+        log_str("Creating New Module...");
 
         let new_module: Module = Module {
             id: self.module_count,
@@ -155,29 +212,45 @@ impl Contract {
             with_ai,
             created_at,
             updated_at: created_at,
+            client_created_at,
             lessons_ids: vec![],
             quizz_id: None,
         };
 
-        // add module to the modules vector
-        self.modules.push(new_module.clone());
+        // add module to the modules map
+        self.modules.insert(new_module.id, new_module.clone());
 
         // add the module id to the course
         let mut course = course.unwrap();
         course.modules_ids.push(self.module_count);
 
         // update the course
-        self.courses.replace(course_id as u32, course.clone());
+        self.courses.insert(course_id, course.clone());
 
         // Log the creation of the module
         log_str(&format!("Module created: {} by {}", title, account_id));
 
+        // emit the module_created event
+        CourchainEvent::ModuleCreated(vec![ModuleCreatedData {
+            module_id: new_module.id,
+            course_id,
+            title,
+            order,
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
         // Increment the module count
         self.module_count += 1;
 
-        return new_module;
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(new_module)
     }
 
+    #[handle_result]
     pub fn save_lesson_by_admin(
         &mut self,
         module_id: u64,
@@ -187,23 +260,20 @@ impl Contract {
         article: String,
         order: u64,
         with_ai: bool,
-        created_at: u64,
-    ) {
-        let account_id: AccountId = env::signer_account_id();
-
-        // only ayoubamer.testnet can save lessons
-        if account_id.to_string() != "ayoubamer.testnet".to_string() {
-            env::panic_str("Only the admin can save lessons")
-        }
+        client_created_at: Option<u64>,
+    ) -> Result<(), ContractError> {
+        // only admins can save lessons
+        let account_id = self.require_role(Role::Admin, "Only the admin can save lessons")?;
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the module exists
         let module = self.get_module_by_id(module_id);
         if module.is_none() {
-            log_str("Module does not exist");
-            env::panic_str("Module does not exist")
+            return Err(ContractError::NotFound("Module does not exist".to_string()));
         }
 
-        log_str(&format!("Creating New Lesson..."));
+        log_str("Creating New Lesson...");
 
         let new_lesson: Lesson = Lesson {
             id: self.lesson_count,
@@ -216,10 +286,11 @@ impl Contract {
             with_ai,
             created_at,
             updated_at: created_at,
+            client_created_at,
         };
 
-        // add lesson to the lessons vector
-        self.lessons.push(new_lesson);
+        // add lesson to the lessons map
+        self.lessons.insert(new_lesson.id, new_lesson);
 
         // Log the creation of the lesson
         log_str(&format!("Lesson created: {} by {}", title, account_id));
@@ -229,15 +300,32 @@ impl Contract {
         module.lessons_ids.push(self.lesson_count);
 
         // update the module
-        self.modules.replace(module_id as u32, module.clone());
+        self.modules.insert(module_id, module.clone());
 
         // Log the addition of the lesson to the module
         log_str(&format!("Lesson added to module: {}", module.title));
 
+        // emit the lesson_created event
+        CourchainEvent::LessonCreated(vec![LessonCreatedData {
+            lesson_id: self.lesson_count,
+            module_id,
+            title,
+            order,
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
         // Increment the lesson count
         self.lesson_count += 1;
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
     }
 
+    #[handle_result]
     pub fn update_lesson_by_admin(
         &mut self,
         lesson_id: u64,
@@ -247,23 +335,18 @@ impl Contract {
         article: String,
         order: u64,
         with_ai: bool,
-        updated_at: u64,
-    ) -> Lesson {
-        let account_id: AccountId = env::signer_account_id();
-
-        // only ayoubamer.testnet can update lessons
-        if account_id.to_string() != "ayoubamer.testnet".to_string() {
-            env::panic_str("Only the admin can update lessons")
-        }
+    ) -> Result<Lesson, ContractError> {
+        // only admins can update lessons
+        let account_id = self.require_role(Role::Admin, "Only the admin can update lessons")?;
+        let initial_storage = env::storage_usage();
 
         // get the lesson
         let lesson = self.get_lesson_by_id(lesson_id);
         if lesson.is_none() {
-            log_str("Lesson does not exist");
-            env::panic_str("Lesson does not exist")
+            return Err(ContractError::NotFound("Lesson does not exist".to_string()));
         }
 
-        log_str(&format!("Updating Lesson..."));
+        log_str("Updating Lesson...");
 
         // update the lesson
         let mut lesson = lesson.unwrap();
@@ -273,17 +356,32 @@ impl Contract {
         lesson.article = article;
         lesson.order = order;
         lesson.with_ai = with_ai;
-        lesson.updated_at = updated_at;
+        lesson.updated_at = env::block_timestamp();
 
         // update the lesson
-        self.lessons.replace(lesson_id as u32, lesson.clone());
+        self.lessons.insert(lesson_id, lesson.clone());
 
         // Log the update of the lesson
         log_str(&format!("Lesson updated: {}", title));
 
-        return lesson;
+        // emit the lesson_updated event
+        CourchainEvent::LessonUpdated(vec![LessonUpdatedData {
+            lesson_id: lesson.id,
+            module_id: lesson.module_id,
+            title: lesson.title.clone(),
+            order: lesson.order,
+            updated_by: account_id.clone(),
+            updated_at: lesson.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(lesson)
     }
 
+    #[handle_result]
     pub fn update_user_min_info_by_admin(
         &mut self,
         account_id: AccountId,
@@ -291,23 +389,25 @@ impl Contract {
         email: String,
         username: String,
         phone: String,
-        updated_at: u64,
-    ) -> User {
-        let admin_account_id: AccountId = env::signer_account_id();
-
-        // only ayoubamer.testnet can update users
-        if admin_account_id.to_string() != "ayoubamer.testnet".to_string() {
-            env::panic_str("Only the admin can update users")
-        }
+    ) -> Result<User, ContractError> {
+        // only admins can update users
+        let admin_id = self.require_role(Role::Admin, "Only the admin can update users")?;
+        let mut initial_storage = env::storage_usage();
 
         // get the user
         let user = self.get_user_by_id(account_id.clone());
         if user.is_none() {
-            log_str("User does not exist");
-            env::panic_str("User does not exist")
+            return Err(ContractError::NotFound("User does not exist".to_string()));
+        }
+
+        // check if the new username or email belong to another user
+        if self.is_username_or_email_taken(&account_id, &username, &email) {
+            return Err(ContractError::AlreadyExists(
+                "Username or email is already taken".to_string(),
+            ));
         }
 
-        log_str(&format!("Updating User..."));
+        log_str("Updating User...");
 
         // update the user
         let mut user = user.unwrap();
@@ -315,21 +415,30 @@ impl Contract {
         user.email = email.clone();
         user.username = username.clone();
         user.phone = phone.clone();
-        user.updated_at = updated_at;
-
-        // find the user index in the users vector
-        let user_index = self
-            .users
-            .iter()
-            .position(|u| u.account_id == user.account_id)
-            .unwrap();
+        user.updated_at = env::block_timestamp();
 
-        // update the user
-        self.users.replace(user_index as u32, user.clone());
+        // update the user, a shorter profile frees storage its owner paid for
+        let owner_storage = self.storage_checkpoint();
+        self.internal_save_user(user.clone());
+        initial_storage = self.release_storage(&account_id, owner_storage, initial_storage);
 
         // Log the update of the user
         log_str(&format!("User updated : {}", account_id));
 
-        return user;
+        // emit the user_updated event
+        CourchainEvent::UserUpdated(vec![UserUpdatedData {
+            account_id: user.account_id.clone(),
+            username: user.username.clone(),
+            name: user.name.clone(),
+            role: user.role,
+            updated_by: admin_id.clone(),
+            updated_at: user.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&admin_id, initial_storage);
+
+        Ok(user)
     }
 }
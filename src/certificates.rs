@@ -0,0 +1,153 @@
+use crate::errors::ContractError;
+use crate::events::*;
+use crate::getters::{page_bounds, MAX_PAGE_LIMIT};
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId};
+
+#[near_bindgen]
+impl Contract {
+    /// Returns `None` only for ids that were never issued; revoked
+    /// certificates are returned with status `revoked`.
+    pub fn verify_certificate(&self, certificate_id: u64) -> Option<CertificateVerification> {
+        self.certificates
+            .get(&certificate_id)
+            .map(|certificate| self.internal_verify_certificate(certificate))
+    }
+
+    pub fn get_certificates_of(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<CertificateVerification> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let mut certificate_list: Vec<CertificateVerification> = vec![];
+        if let Some(certificate_ids) = self.student_certificates.get(&account_id) {
+            for certificate_id in certificate_ids.iter().skip(from_index).take(limit) {
+                if let Some(certificate) = self.certificates.get(certificate_id) {
+                    certificate_list.push(self.internal_verify_certificate(certificate));
+                }
+            }
+        }
+        certificate_list
+    }
+
+    #[handle_result]
+    pub fn revoke_certificate(
+        &mut self,
+        certificate_id: u64,
+        reason: String,
+    ) -> Result<bool, ContractError> {
+        let admin_id = self.require_role(Role::Admin, "Only admins can revoke certificates")?;
+        let mut initial_storage = env::storage_usage();
+
+        // check if the certificate exists
+        let certificate = self.certificates.get(&certificate_id).cloned();
+        if certificate.is_none() {
+            return Err(ContractError::NotFound(
+                "Certificate does not exist".to_string(),
+            ));
+        }
+
+        let mut certificate = certificate.unwrap();
+        if certificate.revoked_at.is_some() {
+            return Err(ContractError::InvalidState(
+                "Certificate is already revoked".to_string(),
+            ));
+        }
+
+        certificate.revoked_at = Some(env::block_timestamp());
+        certificate.revoked_by = Some(admin_id.clone());
+        certificate.revocation_reason = Some(reason.clone());
+
+        // update the certificate, it stays linked from the student and the
+        // enrollment so it is reported as revoked and never issued again
+        self.certificates
+            .insert(certificate_id, certificate.clone());
+        self.revoked_certificate_count += 1;
+
+        // unlink it from the student's profile. The student paid for the
+        // profile entry, they get its storage back
+        if let Some(mut student) = self.get_user_by_id(certificate.student_id.clone()) {
+            if let Some(certifications) = student.certifications.as_mut() {
+                certifications.retain(|certification| certification.id != certificate_id);
+            }
+            let student_storage = self.storage_checkpoint();
+            self.internal_save_user(student);
+            initial_storage =
+                self.release_storage(&certificate.student_id, student_storage, initial_storage);
+        }
+
+        log_str(&format!(
+            "Certificate {} of {} revoked by {}",
+            certificate_id, certificate.student_id, admin_id
+        ));
+
+        // NEP-171 burn event, the token leaves the NFT views
+        log_str(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": "nep171",
+                "version": "1.0.0",
+                "event": "nft_burn",
+                "data": [{
+                    "owner_id": certificate.student_id,
+                    "token_ids": [certificate_id.to_string()],
+                    "memo": reason,
+                }],
+            })
+        ));
+
+        // emit the certificate_revoked event
+        CourchainEvent::CertificateRevoked(vec![CertificateRevokedData {
+            certificate_id,
+            student_id: certificate.student_id.clone(),
+            reason,
+            revoked_by: admin_id.clone(),
+            revoked_at: env::block_timestamp(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&admin_id, initial_storage);
+
+        Ok(true)
+    }
+}
+
+impl Contract {
+    fn internal_verify_certificate(&self, certificate: &Certificate) -> CertificateVerification {
+        // the enrollment is the source of truth for the completion time
+        let completed_at = self
+            .enrollments
+            .get(&certificate.enrollment_id)
+            .and_then(|enrollment| enrollment.completed_at)
+            .unwrap_or(certificate.completed_at);
+
+        let status = if certificate.revoked_at.is_some() {
+            CertificateStatus::Revoked
+        } else {
+            CertificateStatus::Valid
+        };
+
+        CertificateVerification {
+            certificate_id: certificate.id,
+            token_id: certificate.id.to_string(),
+            status,
+            course_id: certificate.course_id,
+            course_title: certificate.title.clone(),
+            mentor_id: certificate.mentor_id.clone(),
+            student_id: certificate.student_id.clone(),
+            completed_at,
+            quizzes_total: certificate.quizzes_total,
+            quizzes_passed: certificate.quizzes_passed,
+            quizz_attempts: certificate.quizz_attempts,
+            revoked_at: certificate.revoked_at,
+            revocation_reason: certificate.revocation_reason.clone(),
+        }
+    }
+}
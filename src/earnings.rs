@@ -0,0 +1,283 @@
+use crate::errors::ContractError;
+use crate::events::*;
+use crate::getters::{page_bounds, MAX_PAGE_LIMIT};
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, is_promise_success, near_bindgen, AccountId, FunctionError, Gas, Promise};
+
+/// Gas attached to the callback that settles a mentor payout.
+const GAS_FOR_RESOLVE_PAYOUT: Gas = Gas(10_000_000_000_000);
+
+/// Gas attached to `ft_transfer` on the token contract.
+pub const GAS_FOR_FT_TRANSFER: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    pub fn withdraw_earnings(&mut self) -> Promise {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        let payout = self.internal_start_payout(&account_id, None);
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Promise::new(account_id.clone())
+            .transfer(payout.amount.0)
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_PAYOUT)
+                    .on_earnings_withdrawn(account_id, payout.id),
+            )
+    }
+
+    pub fn withdraw_token_earnings(&mut self, token_id: AccountId) -> Promise {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        let payout = self.internal_start_payout(&account_id, Some(token_id.clone()));
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Promise::new(token_id)
+            .function_call(
+                "ft_transfer".to_string(),
+                json!({ "receiver_id": account_id, "amount": payout.amount })
+                    .to_string()
+                    .into_bytes(),
+                1,
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_PAYOUT)
+                    .on_earnings_withdrawn(account_id, payout.id),
+            )
+    }
+
+    /// Settles a payout. Does not charge storage: a panic here would keep the
+    /// balance from being restored.
+    #[private]
+    pub fn on_earnings_withdrawn(&mut self, mentor_id: AccountId, payout_id: u64) -> bool {
+        let payout = self
+            .payouts
+            .get_mut(&payout_id)
+            .filter(|payout| payout.mentor_id == mentor_id);
+        if payout.is_none() {
+            log_str(&format!("Payout {} does not exist", payout_id));
+            return false;
+        }
+        let payout = payout.unwrap();
+        let amount = payout.amount.0;
+        let token_id = payout.token_id.clone();
+
+        let success = is_promise_success();
+        let status = if success {
+            PayoutStatus::Completed
+        } else {
+            PayoutStatus::Failed
+        };
+        if let Err(error) = payout.status.transition_to(status) {
+            log_str(&error.to_string());
+            return false;
+        }
+        emit_payout(payout);
+
+        let mut earnings = self.internal_get_earnings(&mentor_id, &token_id);
+        if success {
+            earnings.withdrawn += amount;
+            self.internal_set_earnings(&mentor_id, &token_id, earnings);
+            return true;
+        }
+
+        // the transfer failed, give the balance back to the mentor
+        earnings.pending += amount;
+        self.internal_set_earnings(&mentor_id, &token_id, earnings);
+
+        log_str(&format!(
+            "Payout {} to {} failed, {} returned to the pending balance",
+            payout_id, mentor_id, amount
+        ));
+        false
+    }
+
+    pub fn get_mentor_earnings(&self, mentor_id: AccountId) -> MentorEarningsView {
+        self.internal_earnings_view(mentor_id, None)
+    }
+
+    pub fn get_mentor_token_earnings(
+        &self,
+        mentor_id: AccountId,
+        token_id: AccountId,
+    ) -> MentorEarningsView {
+        self.internal_earnings_view(mentor_id, Some(token_id))
+    }
+
+    /// Payouts of a mentor, oldest first.
+    pub fn get_mentor_payouts(
+        &self,
+        mentor_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Payout> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let mut payout_list: Vec<Payout> = vec![];
+        if let Some(payout_ids) = self.mentor_payouts.get(&mentor_id) {
+            for payout_id in payout_ids.iter().skip(from_index).take(limit) {
+                if let Some(payout) = self.payouts.get(payout_id) {
+                    payout_list.push(payout.clone());
+                }
+            }
+        }
+        payout_list
+    }
+
+    pub fn get_payout_by_id(&self, payout_id: u64) -> Option<Payout> {
+        self.payouts.get(&payout_id).cloned()
+    }
+}
+
+impl Contract {
+    /// Holds a course sale in the mentor's escrowed balance, in NEAR when
+    /// `token_id` is `None` and in that fungible token otherwise.
+    pub(crate) fn internal_escrow_earnings(
+        &mut self,
+        mentor_id: &AccountId,
+        token_id: &Option<AccountId>,
+        amount: u128,
+    ) {
+        let mut earnings = self.internal_get_earnings(mentor_id, token_id);
+        earnings.lifetime += amount;
+        earnings.escrowed += amount;
+        self.internal_set_earnings(mentor_id, token_id, earnings);
+    }
+
+    /// Moves an escrowed sale to the withdrawable balance.
+    pub(crate) fn internal_release_earnings(
+        &mut self,
+        mentor_id: &AccountId,
+        token_id: &Option<AccountId>,
+        amount: u128,
+    ) {
+        let mut earnings = self.internal_get_earnings(mentor_id, token_id);
+        earnings.escrowed -= amount;
+        earnings.pending += amount;
+        self.internal_set_earnings(mentor_id, token_id, earnings);
+    }
+
+    /// Takes the escrowed part of a sale back out of the mentor's earnings on
+    /// refund. The upfront share was already released and is not touched.
+    pub(crate) fn internal_claw_back_earnings(
+        &mut self,
+        mentor_id: &AccountId,
+        token_id: &Option<AccountId>,
+        amount: u128,
+    ) {
+        let mut earnings = self.internal_get_earnings(mentor_id, token_id);
+        earnings.lifetime -= amount;
+        earnings.escrowed -= amount;
+        self.internal_set_earnings(mentor_id, token_id, earnings);
+    }
+
+    fn internal_get_earnings(
+        &self,
+        mentor_id: &AccountId,
+        token_id: &Option<AccountId>,
+    ) -> MentorEarnings {
+        let earnings = match token_id {
+            None => self.mentor_earnings.get(mentor_id),
+            Some(token_id) => self
+                .token_earnings
+                .get(&(mentor_id.clone(), token_id.clone())),
+        };
+        earnings.cloned().unwrap_or_default()
+    }
+
+    fn internal_set_earnings(
+        &mut self,
+        mentor_id: &AccountId,
+        token_id: &Option<AccountId>,
+        earnings: MentorEarnings,
+    ) {
+        match token_id {
+            None => {
+                self.mentor_earnings.insert(mentor_id.clone(), earnings);
+            }
+            Some(token_id) => {
+                self.token_earnings
+                    .insert((mentor_id.clone(), token_id.clone()), earnings);
+            }
+        }
+    }
+
+    fn internal_earnings_view(
+        &self,
+        mentor_id: AccountId,
+        token_id: Option<AccountId>,
+    ) -> MentorEarningsView {
+        let earnings = self.internal_get_earnings(&mentor_id, &token_id);
+
+        MentorEarningsView {
+            mentor_id,
+            token_id,
+            lifetime: U128(earnings.lifetime),
+            escrowed: U128(earnings.escrowed),
+            pending: U128(earnings.pending),
+            withdrawn: U128(earnings.withdrawn),
+        }
+    }
+
+    /// Moves the whole pending balance into a new pending payout record,
+    /// settled later by `on_earnings_withdrawn`.
+    fn internal_start_payout(
+        &mut self,
+        mentor_id: &AccountId,
+        token_id: Option<AccountId>,
+    ) -> Payout {
+        let mut earnings = self.internal_get_earnings(mentor_id, &token_id);
+        if earnings.pending == 0 {
+            ContractError::InvalidState("There are no earnings to withdraw".to_string()).panic()
+        }
+
+        // debit first, the callback credits it back if the transfer fails
+        let amount = earnings.pending;
+        earnings.pending = 0;
+        self.internal_set_earnings(mentor_id, &token_id, earnings);
+
+        let payout = Payout {
+            id: self.payout_count,
+            mentor_id: mentor_id.clone(),
+            token_id,
+            amount: U128(amount),
+            status: PayoutStatus::Pending,
+            requested_at: env::block_timestamp(),
+        };
+        self.payouts.insert(payout.id, payout.clone());
+        self.mentor_payouts
+            .entry(mentor_id.clone())
+            .or_default()
+            .push(payout.id);
+        self.payout_count += 1;
+
+        log_str(&format!("Paying out {} to mentor: {}", amount, mentor_id));
+        emit_payout(&payout);
+
+        payout
+    }
+}
+
+fn emit_payout(payout: &Payout) {
+    CourchainEvent::Payout(vec![PayoutData {
+        payout_id: payout.id,
+        mentor_id: payout.mentor_id.clone(),
+        token_id: payout.token_id.clone(),
+        amount: payout.amount,
+        status: payout.status,
+    }])
+    .emit();
+}
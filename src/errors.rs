@@ -0,0 +1,45 @@
+use near_sdk::FunctionError;
+use std::fmt;
+
+/// Error returned by the `#[handle_result]` entrypoints. A failed call
+/// panics with `"<CODE>: <message>"`; the codes are stable so clients can
+/// match on them, the messages are for humans.
+#[derive(FunctionError, Debug, Clone, PartialEq, Eq)]
+pub enum ContractError {
+    NotFound(String),
+    Unauthorized(String),
+    InvalidInput(String),
+    InvalidState(String),
+    InsufficientDeposit(String),
+    AlreadyExists(String),
+}
+
+impl ContractError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContractError::NotFound(_) => "NOT_FOUND",
+            ContractError::Unauthorized(_) => "UNAUTHORIZED",
+            ContractError::InvalidInput(_) => "INVALID_INPUT",
+            ContractError::InvalidState(_) => "INVALID_STATE",
+            ContractError::InsufficientDeposit(_) => "INSUFFICIENT_DEPOSIT",
+            ContractError::AlreadyExists(_) => "ALREADY_EXISTS",
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ContractError::NotFound(message)
+            | ContractError::Unauthorized(message)
+            | ContractError::InvalidInput(message)
+            | ContractError::InvalidState(message)
+            | ContractError::InsufficientDeposit(message)
+            | ContractError::AlreadyExists(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.code(), self.message())
+    }
+}
@@ -0,0 +1,391 @@
+use crate::models::{CourseStatus, PaymentStatus, PayoutStatus, UserRole};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::serde::Serialize;
+use near_sdk::serde_json;
+use near_sdk::AccountId;
+
+pub const EVENT_STANDARD: &str = "courchain";
+pub const EVENT_VERSION: &str = "1.0.0";
+
+/// NEP-297 events of the `courchain` standard, logged as
+/// `EVENT_JSON:{"standard":"courchain","version":"1.0.0","event":...,"data":[...]}`.
+/// Every change to users, content, enrollments, payments, reviews and
+/// certificates is logged. Update events carry ids, titles and prices only:
+/// contact details, lesson bodies, quizz questions and platform settings are
+/// read from the getters.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+#[serde(tag = "event", content = "data", rename_all = "snake_case")]
+pub enum CourchainEvent {
+    UserCreated(Vec<UserCreatedData>),
+    UserUpdated(Vec<UserUpdatedData>),
+    CourseCreated(Vec<CourseCreatedData>),
+    CourseUpdated(Vec<CourseUpdatedData>),
+    CoursePublished(Vec<CourseStatusData>),
+    CourseArchived(Vec<CourseStatusData>),
+    ModuleCreated(Vec<ModuleCreatedData>),
+    ModuleUpdated(Vec<ModuleUpdatedData>),
+    LessonCreated(Vec<LessonCreatedData>),
+    LessonUpdated(Vec<LessonUpdatedData>),
+    QuizCreated(Vec<QuizCreatedData>),
+    QuizQuestionsSaved(Vec<QuizQuestionsSavedData>),
+    LessonDeleted(Vec<LessonDeletedData>),
+    QuizDeleted(Vec<QuizDeletedData>),
+    ModuleDeleted(Vec<ModuleDeletedData>),
+    CourseDeleted(Vec<CourseDeletedData>),
+    LessonsReordered(Vec<LessonsReorderedData>),
+    ModulesReordered(Vec<ModulesReorderedData>),
+    CourseCarted(Vec<CourseCartedData>),
+    CourseRemovedFromCart(Vec<CourseRemovedFromCartData>),
+    Enrolled(Vec<EnrolledData>),
+    LessonCompleted(Vec<LessonCompletedData>),
+    QuizSubmitted(Vec<QuizSubmittedData>),
+    CourseCompleted(Vec<CourseCompletedData>),
+    Payout(Vec<PayoutData>),
+    Refund(Vec<RefundData>),
+    EscrowReleased(Vec<EscrowReleasedData>),
+    ReviewSubmitted(Vec<ReviewSubmittedData>),
+    ReviewReplied(Vec<ReviewRepliedData>),
+    ReviewHidden(Vec<ReviewHiddenData>),
+    ReviewRemoved(Vec<ReviewRemovedData>),
+    CertificateRevoked(Vec<CertificateRevokedData>),
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+struct EventLog<'a> {
+    standard: &'static str,
+    version: &'static str,
+    #[serde(flatten)]
+    event: &'a CourchainEvent,
+}
+
+impl CourchainEvent {
+    pub fn emit(&self) {
+        let log = EventLog {
+            standard: EVENT_STANDARD,
+            version: EVENT_VERSION,
+            event: self,
+        };
+        log_str(&format!(
+            "EVENT_JSON:{}",
+            serde_json::to_string(&log).unwrap()
+        ));
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UserCreatedData {
+    pub account_id: AccountId,
+    pub username: String,
+    pub role: UserRole,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct UserUpdatedData {
+    pub account_id: AccountId,
+    pub username: String,
+    pub name: String,
+    pub role: UserRole,
+    pub updated_by: AccountId,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseCreatedData {
+    pub course_id: u64,
+    pub mentor_id: AccountId,
+    pub title: String,
+    pub price: U128,
+    pub created_by: AccountId,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseUpdatedData {
+    pub course_id: u64,
+    pub title: String,
+    pub price: U128,
+    pub category: String,
+    pub updated_by: AccountId,
+    pub updated_at: u64,
+}
+
+/// Payload of `course_published` and `course_archived`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseStatusData {
+    pub course_id: u64,
+    pub status: CourseStatus,
+    pub changed_by: AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModuleCreatedData {
+    pub module_id: u64,
+    pub course_id: u64,
+    pub title: String,
+    pub order: u64,
+    pub created_by: AccountId,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModuleUpdatedData {
+    pub module_id: u64,
+    pub title: String,
+    pub updated_by: AccountId,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LessonCreatedData {
+    pub lesson_id: u64,
+    pub module_id: u64,
+    pub title: String,
+    pub order: u64,
+    pub created_by: AccountId,
+    pub created_at: u64,
+}
+
+/// Logged for title, video, article and order changes alike.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LessonUpdatedData {
+    pub lesson_id: u64,
+    pub module_id: u64,
+    pub title: String,
+    pub order: u64,
+    pub updated_by: AccountId,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizCreatedData {
+    pub quizz_id: u64,
+    pub module_id: u64,
+    pub title: String,
+    pub created_by: AccountId,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizQuestionsSavedData {
+    pub quizz_id: u64,
+    pub module_id: u64,
+    pub questions_count: u32,
+    pub with_ai: bool,
+    pub saved_by: AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LessonDeletedData {
+    pub lesson_id: u64,
+    pub module_id: u64,
+    pub deleted_by: AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizDeletedData {
+    pub quizz_id: u64,
+    pub module_id: u64,
+    pub deleted_by: AccountId,
+}
+
+/// The lessons and quizz of the module are deleted with it.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModuleDeletedData {
+    pub module_id: u64,
+    pub course_id: u64,
+    pub lessons_ids: Vec<u64>,
+    pub quizz_id: Option<u64>,
+    pub deleted_by: AccountId,
+}
+
+/// The modules of the course are deleted with it, see `ModuleDeletedData`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseDeletedData {
+    pub course_id: u64,
+    pub modules_ids: Vec<u64>,
+    pub deleted_by: AccountId,
+}
+
+/// `lessons_ids` is the new order, the lesson at index `i` has order `i + 1`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LessonsReorderedData {
+    pub module_id: u64,
+    pub lessons_ids: Vec<u64>,
+    pub reordered_by: AccountId,
+}
+
+/// `modules_ids` is the new order, the module at index `i` has order `i + 1`.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModulesReorderedData {
+    pub course_id: u64,
+    pub modules_ids: Vec<u64>,
+    pub reordered_by: AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseCartedData {
+    pub enrollment_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub carted_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseRemovedFromCartData {
+    pub enrollment_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+}
+
+/// `token_id` is `None` for courses paid in NEAR.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EnrolledData {
+    pub enrollment_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub mentor_id: AccountId,
+    pub token_id: Option<AccountId>,
+    pub price: U128,
+    pub fee: U128,
+    pub enrolled_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct LessonCompletedData {
+    pub lesson_id: u64,
+    pub module_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub completed_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizSubmittedData {
+    pub quizz_id: u64,
+    pub module_id: u64,
+    pub student_id: AccountId,
+    pub correct_count: u32,
+    pub total_questions: u32,
+    pub passed: bool,
+    pub try_count: u16,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseCompletedData {
+    pub enrollment_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub certificate_id: Option<u64>,
+    pub completed_at: u64,
+}
+
+/// Logged when a payout starts (status `pending`) and again when it settles.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PayoutData {
+    pub payout_id: u64,
+    pub mentor_id: AccountId,
+    pub token_id: Option<AccountId>,
+    pub amount: U128,
+    pub status: PayoutStatus,
+}
+
+/// Logged when a refund starts (status `refunding`) and again when its
+/// transfer resolves: `refunded`, or `escrowed` if the transfer failed.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundData {
+    pub enrollment_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub token_id: Option<AccountId>,
+    pub amount: U128,
+    pub status: PaymentStatus,
+}
+
+/// Logged when a payment settles (status `settled`, `fee` goes to the
+/// treasury) and when the rest of its escrow is released (status `released`).
+/// `released` is the amount credited to the mentor at that step.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EscrowReleasedData {
+    pub enrollment_id: u64,
+    pub mentor_id: AccountId,
+    pub token_id: Option<AccountId>,
+    pub fee: U128,
+    pub released: U128,
+    pub status: PaymentStatus,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReviewSubmittedData {
+    pub review_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub rating: u8,
+    pub created_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReviewRepliedData {
+    pub review_id: u64,
+    pub course_id: u64,
+    pub mentor_id: AccountId,
+    pub replied_at: u64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReviewHiddenData {
+    pub review_id: u64,
+    pub course_id: u64,
+    pub hidden_by: AccountId,
+}
+
+/// The review of a refunded enrollment, deleted with its rating.
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ReviewRemovedData {
+    pub review_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertificateRevokedData {
+    pub certificate_id: u64,
+    pub student_id: AccountId,
+    pub reason: String,
+    pub revoked_by: AccountId,
+    pub revoked_at: u64,
+}
@@ -1,107 +1,226 @@
+use crate::errors::ContractError;
 use crate::models::*;
 use crate::{Contract, ContractExt};
 use near_sdk::{near_bindgen, AccountId};
+use std::collections::BTreeSet;
+
+/// Page size of the list getters when no `limit` is given.
+pub const DEFAULT_PAGE_LIMIT: u64 = 50;
+
+/// Largest page the list getters return.
+pub const MAX_PAGE_LIMIT: u64 = 100;
+
+/// Largest page of `get_full_courses`, every course expands all of its
+/// modules and lessons.
+pub const MAX_FULL_COURSES_PAGE_LIMIT: u64 = 10;
+
+/// Most catalog entries one `search_courses` call looks at.
+pub const MAX_SEARCH_SCAN: u64 = 200;
+
+/// Largest id and enrollment range of `audit_integrity`, every id of one is
+/// looked up for every student of the other.
+pub const MAX_AUDIT_PAGE_LIMIT: u64 = 20;
 
 #[near_bindgen]
 impl Contract {
-    pub fn get_quizzes(&self) -> Vec<Quizz> {
-        let mut quizz_list: Vec<Quizz> = vec![];
-        for quizz in self.quizzes.iter() {
-            quizz_list.push(quizz.clone());
+    pub fn get_quizzes(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<QuizzView> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let mut quizz_list: Vec<QuizzView> = vec![];
+        for quizz in self.quizzes.values().skip(from_index).take(limit) {
+            quizz_list.push(QuizzView::from(quizz));
         }
         quizz_list
     }
 
-    pub fn get_users(&self) -> Vec<User> {
+    pub fn get_users(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<User> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut user_list: Vec<User> = vec![];
-        for user in self.users.iter() {
+        for user in self.users.values().skip(from_index).take(limit) {
             user_list.push(user.clone()); // Clone each User struct
         }
         user_list
     }
 
-    pub fn get_courses(&self) -> Vec<Course> {
+    pub fn get_courses(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<Course> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut course_list: Vec<Course> = vec![];
-        for course in self.courses.iter() {
+        for course in self.courses.values().skip(from_index).take(limit) {
             course_list.push(course.clone());
         }
         course_list
     }
 
-    pub fn get_full_courses(&self) -> Vec<FullCourse> {
-        let mut full_courses_list: Vec<FullCourse> = vec![];
-        for course in self.courses.iter() {
-            let full_course = self.get_full_course(course.id);
-            full_courses_list.push(full_course.unwrap().clone())
-        }
-        full_courses_list
+    pub fn get_full_courses(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<FullCourse> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_FULL_COURSES_PAGE_LIMIT);
+
+        // courses whose mentor has no profile have no full view
+        self.courses
+            .values()
+            .skip(from_index)
+            .take(limit)
+            .filter_map(|course| self.get_full_course(course.id))
+            .collect()
     }
 
-    pub fn get_modules(&self) -> Vec<Module> {
+    pub fn get_modules(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<Module> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut module_list: Vec<Module> = vec![];
-        for module in self.modules.iter() {
+        for module in self.modules.values().skip(from_index).take(limit) {
             module_list.push(module.clone());
         }
         module_list
     }
 
-    pub fn get_lessons(&self) -> Vec<Lesson> {
+    pub fn get_lessons(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<Lesson> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut lesson_list: Vec<Lesson> = vec![];
-        for lesson in self.lessons.iter() {
+        for lesson in self.lessons.values().skip(from_index).take(limit) {
             lesson_list.push(lesson.clone());
         }
         lesson_list
     }
 
-    pub fn get_user_by_id(&self, id: AccountId) -> Option<User> {
-        for user in self.users.iter() {
-            if user.account_id == id {
-                return Some(user.clone());
+    /// Catalog search. Every `filter` field is optional and they all have to
+    /// match. Pages walk the catalog in course id order: one call looks at
+    /// course ids from `from_index`, at most `MAX_SEARCH_SCAN` of them, stops
+    /// at `limit` matches and returns the id the next call resumes from in
+    /// `next_index`. `sort` only orders the matches of the returned page,
+    /// ties broken by course id; without it they stay in id order, the only
+    /// order that holds across pages.
+    pub fn search_courses(
+        &self,
+        filter: CourseFilter,
+        sort: Option<CourseSort>,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> CourseSearchPage {
+        let (_, limit) = page_bounds(None, limit, MAX_PAGE_LIMIT);
+        let from_id = from_index.unwrap_or(0);
+
+        // the mentor index narrows the scan to the mentor's own courses, its
+        // ids are pushed in creation order
+        let scan = MAX_SEARCH_SCAN as usize + 1;
+        let course_ids: Vec<u64> = match &filter.mentor_id {
+            Some(mentor_id) => self
+                .mentor_courses
+                .get(mentor_id)
+                .map(|course_ids| {
+                    course_ids
+                        .iter()
+                        .copied()
+                        .filter(|course_id| *course_id >= from_id)
+                        .take(scan)
+                        .collect()
+                })
+                .unwrap_or_default(),
+            None => (from_id..self.course_count).take(scan).collect(),
+        };
+
+        let mut course_list: Vec<Course> = vec![];
+        let mut next_index: Option<u64> = None;
+        for (scanned, course_id) in course_ids.iter().enumerate() {
+            if course_list.len() == limit || scanned == MAX_SEARCH_SCAN as usize {
+                next_index = Some(*course_id);
+                break;
+            }
+            if let Some(course) = self.courses.get(course_id) {
+                if filter.matches(course) {
+                    course_list.push(course.clone());
+                }
             }
         }
-        None
-    }
 
-    pub fn get_quizz_by_id(&self, quizz_id: u64) -> Option<Quizz> {
-        for quizz in self.quizzes.iter() {
-            if quizz.id == quizz_id {
-                return Some(quizz.clone());
+        match sort {
+            None => {}
+            Some(CourseSort::Newest) => course_list.sort_by(|a, b| {
+                b.created_at
+                    .cmp(&a.created_at)
+                    .then_with(|| b.id.cmp(&a.id))
+            }),
+            Some(CourseSort::PriceAsc) => {
+                course_list.sort_by(|a, b| a.price.cmp(&b.price).then_with(|| a.id.cmp(&b.id)))
             }
+            Some(CourseSort::PriceDesc) => {
+                course_list.sort_by(|a, b| b.price.cmp(&a.price).then_with(|| a.id.cmp(&b.id)))
+            }
+            Some(CourseSort::Rating) => course_list.sort_by(|a, b| {
+                b.average_rating
+                    .total_cmp(&a.average_rating)
+                    .then_with(|| b.rating_count.cmp(&a.rating_count))
+                    .then_with(|| a.id.cmp(&b.id))
+            }),
+        }
+
+        CourseSearchPage {
+            courses: course_list,
+            next_index,
         }
-        None
     }
 
-    pub fn get_user_by_username(&self, username: String) -> Option<User> {
-        for user in self.users.iter() {
-            if user.username == username {
-                return Some(user.clone());
-            }
+    pub fn get_user_by_id(&self, id: AccountId) -> Option<User> {
+        self.users.get(&id).cloned()
+    }
+
+    pub fn get_quizz_by_id(&self, quizz_id: u64) -> Option<QuizzView> {
+        self.internal_get_quizz(quizz_id)
+            .map(|quizz| QuizzView::from(&quizz))
+    }
+
+    /// Full quizz with the correct answers, for `account_id` when it is the
+    /// mentor of its course or an admin. Views have no signer and contract
+    /// state is public, so this keeps the answers out of the student views
+    /// rather than hiding them.
+    #[handle_result]
+    pub fn get_quizz_with_answers(
+        &self,
+        quizz_id: u64,
+        account_id: AccountId,
+    ) -> Result<Quizz, ContractError> {
+        // check if the quizz exists
+        let quizz = self.internal_get_quizz(quizz_id);
+        if quizz.is_none() {
+            return Err(ContractError::NotFound("Quizz does not exist".to_string()));
         }
-        None
+        let quizz = quizz.unwrap();
+
+        // only the mentor of the course and admins see the answers
+        let is_mentor = self
+            .get_module_by_id(quizz.module_id)
+            .and_then(|module| self.get_course_by_id(module.course_id))
+            .is_some_and(|course| course.mentor_id == account_id)
+            && self.has_role(account_id.clone(), Role::Mentor);
+        if !is_mentor && !self.has_role(account_id, Role::Admin) {
+            return Err(ContractError::Unauthorized(
+                "Only the mentor of the course and admins can see the answers".to_string(),
+            ));
+        }
+
+        Ok(quizz)
+    }
+
+    pub fn get_user_by_username(&self, username: String) -> Option<User> {
+        let account_id = self.usernames.get(&username)?;
+        self.get_user_by_id(account_id.clone())
     }
 
     pub fn get_user_by_email(&self, email: String) -> Option<User> {
-        for user in self.users.iter() {
-            if user.email == email {
-                return Some(user.clone());
-            }
-        }
-        None
+        let account_id = self.emails.get(&email)?;
+        self.get_user_by_id(account_id.clone())
     }
 
     pub fn get_course_by_id(&self, course_id: u64) -> Option<Course> {
-        for course in self.courses.iter() {
-            if course.id == course_id {
-                return Some(course.clone());
-            }
-        }
-        None
+        self.courses.get(&course_id).cloned()
     }
 
     pub fn get_user_carted_courses(&self, account_id: AccountId) -> Vec<Course> {
         let mut course_list: Vec<Course> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if enrollment.student_id == account_id && enrollment.status == "carted" {
+        for enrollment in self.internal_student_enrollments(&account_id).iter() {
+            if enrollment.status == EnrollmentStatus::Carted {
                 let course = self.get_course_by_id(enrollment.course_id);
                 if let Some(course) = course {
                     course_list.push(course);
@@ -113,8 +232,8 @@ impl Contract {
 
     pub fn get_user_carted_enrollments(&self, account_id: AccountId) -> Vec<Enrollment> {
         let mut enrollment_list: Vec<Enrollment> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if enrollment.student_id == account_id && enrollment.status == "carted" {
+        for enrollment in self.internal_student_enrollments(&account_id).iter() {
+            if enrollment.status == EnrollmentStatus::Carted {
                 enrollment_list.push(enrollment.clone());
             }
         }
@@ -135,137 +254,168 @@ impl Contract {
         module_list
     }
 
-    pub fn get_course_students(&self, course_id: u64) -> Vec<User> {
+    pub fn get_course_students(
+        &self,
+        course_id: u64,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<User> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut student_list: Vec<User> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if enrollment.course_id == course_id
-                && (enrollment.status == "enrolled" || enrollment.status == "completed")
-            {
-                let student = self.get_user_by_id(enrollment.student_id.clone());
-                if let Some(student) = student {
-                    student_list.push(student);
-                }
+        if let Some(enrollment_ids) = self.course_enrollments.get(&course_id) {
+            let students = enrollment_ids
+                .iter()
+                .filter_map(|enrollment_id| self.enrollments.get(enrollment_id))
+                .filter(|enrollment| {
+                    enrollment.status == EnrollmentStatus::Enrolled
+                        || enrollment.status == EnrollmentStatus::Completed
+                })
+                .filter_map(|enrollment| self.get_user_by_id(enrollment.student_id.clone()));
+            for student in students.skip(from_index).take(limit) {
+                student_list.push(student);
             }
         }
         student_list
     }
 
     pub fn get_module_by_id(&self, module_id: u64) -> Option<Module> {
-        for module in self.modules.iter() {
-            if module.id == module_id {
-                return Some(module.clone());
-            }
-        }
-        None
+        self.modules.get(&module_id).cloned()
     }
 
     pub fn get_module_lessons(&self, module_id: u64) -> Vec<Lesson> {
         let mut lesson_list: Vec<Lesson> = vec![];
-        for lesson in self.lessons.iter() {
-            if lesson.module_id == module_id {
-                lesson_list.push(lesson.clone());
+        if let Some(module) = self.modules.get(&module_id) {
+            for lesson_id in module.lessons_ids.iter() {
+                if let Some(lesson) = self.lessons.get(lesson_id) {
+                    lesson_list.push(lesson.clone());
+                }
             }
         }
         lesson_list
     }
 
-    pub fn get_published_courses(&self) -> Vec<Course> {
+    pub fn get_published_courses(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Course> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut course_list: Vec<Course> = vec![];
-        for course in self.courses.iter() {
-            if course.status == "published" {
-                course_list.push(course.clone());
-            }
+        let courses = self
+            .courses
+            .values()
+            .filter(|course| course.status == CourseStatus::Published);
+        for course in courses.skip(from_index).take(limit) {
+            course_list.push(course.clone());
         }
         course_list
     }
 
-    pub fn get_archived_courses(&self) -> Vec<Course> {
+    pub fn get_archived_courses(&self, from_index: Option<u64>, limit: Option<u64>) -> Vec<Course> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut course_list: Vec<Course> = vec![];
-        for course in self.courses.iter() {
-            if course.status == "archived" {
-                course_list.push(course.clone());
-            }
+        let courses = self
+            .courses
+            .values()
+            .filter(|course| course.status == CourseStatus::Archived);
+        for course in courses.skip(from_index).take(limit) {
+            course_list.push(course.clone());
         }
         course_list
     }
 
     pub fn get_enrollment(&self, course_id: u64, account_id: AccountId) -> Option<Enrollment> {
-        for enrollment in self.enrollments.iter() {
-            if enrollment.course_id == course_id && enrollment.student_id == account_id {
-                return Some(enrollment.clone());
+        let enrollment_id = self.enrollment_ids.get(&(course_id, account_id))?;
+        self.enrollments.get(enrollment_id).cloned()
+    }
+
+    pub fn get_course_enrollments(
+        &self,
+        course_id: u64,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Enrollment> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let mut enrollment_list: Vec<Enrollment> = vec![];
+        if let Some(enrollment_ids) = self.course_enrollments.get(&course_id) {
+            for enrollment_id in enrollment_ids.iter().skip(from_index).take(limit) {
+                if let Some(enrollment) = self.enrollments.get(enrollment_id) {
+                    enrollment_list.push(enrollment.clone());
+                }
             }
         }
-        None
+        enrollment_list
     }
 
-    pub fn get_mentor_created_courses(&self, mentor_id: AccountId) -> Vec<CourseWithProgress> {
+    pub fn get_mentor_created_courses(
+        &self,
+        mentor_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<CourseWithProgress> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut course_list: Vec<CourseWithProgress> = vec![];
-        for course in self.courses.iter() {
-            if course.mentor_id == mentor_id {
-                let progress = 0;
-                let course_with_progress = CourseWithProgress {
-                    id: course.id,
-                    title: course.title.clone(),
-                    description: course.description.clone(),
-                    status: course.status.clone(),
-                    created_at: course.created_at,
-                    updated_at: course.updated_at,
-                    mentor_id: course.mentor_id.clone(),
-                    level: course.level.clone(),
-                    duration: course.duration.clone(),
-                    requirements: course.requirements.clone(),
-                    objectives: course.objectives.clone(),
-                    category: course.category.clone(),
-                    picture: course.picture.clone(),
-                    with_ai: course.with_ai,
-                    price: course.price,
-                    modules_ids: course.modules_ids.clone(),
-                    progress,
-                };
-                course_list.push(course_with_progress);
-            }
+        let courses = self.get_user_created_courses(mentor_id);
+        for course in courses.iter().skip(from_index).take(limit) {
+            let progress = self.calculate_course_creation_progress(course.clone());
+            let course_with_progress = CourseWithProgress {
+                id: course.id,
+                title: course.title.clone(),
+                description: course.description.clone(),
+                status: course.status,
+                created_at: course.created_at,
+                updated_at: course.updated_at,
+                mentor_id: course.mentor_id.clone(),
+                level: course.level.clone(),
+                duration: course.duration.clone(),
+                requirements: course.requirements.clone(),
+                objectives: course.objectives.clone(),
+                category: course.category.clone(),
+                picture: course.picture.clone(),
+                with_ai: course.with_ai,
+                price: course.price,
+                modules_ids: course.modules_ids.clone(),
+                progress,
+            };
+            course_list.push(course_with_progress);
         }
         course_list
     }
 
     pub fn get_student_courses(&self, student_id: AccountId) -> Vec<Course> {
         let mut course_list: Vec<Course> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if enrollment.student_id == student_id {
-                let course = self.get_course_by_id(enrollment.course_id);
-                if let Some(course) = course {
-                    course_list.push(course);
-                }
+        for enrollment in self.internal_student_enrollments(&student_id).iter() {
+            let course = self.get_course_by_id(enrollment.course_id);
+            if let Some(course) = course {
+                course_list.push(course);
             }
         }
         course_list
     }
 
     pub fn get_lesson_by_id(&self, lesson_id: u64) -> Option<Lesson> {
-        for lesson in self.lessons.iter() {
-            if lesson.id == lesson_id {
-                return Some(lesson.clone());
-            }
-        }
-        None
+        self.lessons.get(&lesson_id).cloned()
     }
 
-    pub fn get_module_quizz(&self, module: Module) -> Option<Quizz> {
+    pub fn get_module_quizz(&self, module: Module) -> Option<QuizzView> {
         if let Some(quizz_id) = module.quizz_id {
-            for quizz in self.quizzes.iter() {
-                if quizz.id == quizz_id {
-                    return Some(quizz.clone());
-                }
-            }
+            return self.get_quizz_by_id(quizz_id);
         }
         None
     }
+
+    /// `None` when the course or its mentor's profile does not exist.
     pub fn get_full_course(&self, course_id: u64) -> Option<FullCourse> {
         let course = self.get_course_by_id(course_id);
         if let Some(course) = course {
             let mut modules: Vec<FullModule> = vec![];
 
-            let mentor = self.get_user_by_id(course.mentor_id.clone());
+            let mentor = self.get_user_by_id(course.mentor_id.clone())?;
 
             for module_id in course.modules_ids.iter() {
                 let module = self.get_module_by_id(*module_id);
@@ -288,6 +438,7 @@ impl Contract {
                             });
                         }
                     }
+                    lessons.sort_by_key(|lesson| (lesson.order, lesson.id));
                     modules.push(FullModule {
                         id: module.id,
                         title: module.title,
@@ -303,11 +454,13 @@ impl Contract {
                 }
             }
 
+            modules.sort_by_key(|module| (module.order, module.id));
+
             return Some(FullCourse {
                 id: course.id,
                 title: course.title.clone(),
                 description: course.description.clone(),
-                status: course.status.clone(),
+                status: course.status,
                 created_at: course.created_at,
                 updated_at: course.updated_at,
                 modules,
@@ -320,7 +473,9 @@ impl Contract {
                 picture: course.picture.clone(),
                 with_ai: course.with_ai,
                 price: course.price,
-                mentor: mentor.unwrap(),
+                mentor,
+                rating_count: course.rating_count,
+                average_rating: course.average_rating,
             });
         }
         None
@@ -331,12 +486,9 @@ impl Contract {
         lesson_id: u64,
         student_id: AccountId,
     ) -> Option<LessonProgress> {
-        for lesson_progress in self.lesson_progresses.iter() {
-            if lesson_progress.lesson_id == lesson_id && lesson_progress.student_id == student_id {
-                return Some(lesson_progress.clone());
-            }
-        }
-        None
+        self.lesson_progresses
+            .get(&(lesson_id, student_id))
+            .cloned()
     }
 
     pub fn get_module_progress(
@@ -344,12 +496,9 @@ impl Contract {
         module_id: u64,
         student_id: AccountId,
     ) -> Option<ModuleProgress> {
-        for module_progress in self.module_progresses.iter() {
-            if module_progress.module_id == module_id && module_progress.student_id == student_id {
-                return Some(module_progress.clone());
-            }
-        }
-        None
+        self.module_progresses
+            .get(&(module_id, student_id))
+            .cloned()
     }
 
     pub fn get_user_carted_courses_prices_with_fee(&self, account_id: AccountId) -> u128 {
@@ -359,11 +508,20 @@ impl Contract {
         total_price
     }
 
-    pub fn get_user_enrollments(&self, account_id: AccountId) -> Vec<Enrollment> {
+    pub fn get_user_enrollments(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Enrollment> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
         let mut enrollment_list: Vec<Enrollment> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if enrollment.student_id == account_id {
-                enrollment_list.push(enrollment.clone());
+        if let Some(enrollment_ids) = self.student_enrollments.get(&account_id) {
+            for enrollment_id in enrollment_ids.iter().skip(from_index).take(limit) {
+                if let Some(enrollment) = self.enrollments.get(enrollment_id) {
+                    enrollment_list.push(enrollment.clone());
+                }
             }
         }
         enrollment_list
@@ -371,9 +529,9 @@ impl Contract {
 
     pub fn get_user_enrolled_courses(&self, account_id: AccountId) -> Vec<Course> {
         let mut course_list: Vec<Course> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if (enrollment.student_id == account_id)
-                && (enrollment.status == "enrolled" || enrollment.status == "completed")
+        for enrollment in self.internal_student_enrollments(&account_id).iter() {
+            if enrollment.status == EnrollmentStatus::Enrolled
+                || enrollment.status == EnrollmentStatus::Completed
             {
                 let course = self.get_course_by_id(enrollment.course_id);
                 if let Some(course) = course {
@@ -384,10 +542,17 @@ impl Contract {
         course_list
     }
 
-    pub fn get_user_enrolled_full_courses(&self, account_id: AccountId) -> Vec<FullCourse> {
+    pub fn get_user_enrolled_full_courses(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<FullCourse> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_FULL_COURSES_PAGE_LIMIT);
+
         let mut full_course_list: Vec<FullCourse> = vec![];
         let enrolled_courses = self.get_user_enrolled_courses(account_id);
-        for course in enrolled_courses.iter() {
+        for course in enrolled_courses.iter().skip(from_index).take(limit) {
             let full_course = self.get_full_course(course.id);
             if let Some(full_course) = full_course {
                 full_course_list.push(full_course);
@@ -398,8 +563,8 @@ impl Contract {
 
     pub fn get_user_completed_courses(&self, account_id: AccountId) -> Vec<Course> {
         let mut course_list: Vec<Course> = vec![];
-        for enrollment in self.enrollments.iter() {
-            if enrollment.student_id == account_id && enrollment.status == "completed" {
+        for enrollment in self.internal_student_enrollments(&account_id).iter() {
+            if enrollment.status == EnrollmentStatus::Completed {
                 let course = self.get_course_by_id(enrollment.course_id);
                 if let Some(course) = course {
                     course_list.push(course);
@@ -409,10 +574,17 @@ impl Contract {
         course_list
     }
 
-    pub fn get_user_completed_full_courses(&self, account_id: AccountId) -> Vec<FullCourse> {
+    pub fn get_user_completed_full_courses(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<FullCourse> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_FULL_COURSES_PAGE_LIMIT);
+
         let mut full_course_list: Vec<FullCourse> = vec![];
         let enrolled_courses = self.get_user_completed_courses(account_id);
-        for course in enrolled_courses.iter() {
+        for course in enrolled_courses.iter().skip(from_index).take(limit) {
             let full_course = self.get_full_course(course.id);
             if let Some(full_course) = full_course {
                 full_course_list.push(full_course);
@@ -441,6 +613,8 @@ impl Contract {
                     });
                 }
             }
+            lessons.sort_by_key(|lesson| (lesson.order, lesson.id));
+
             let quizz = self.get_module_quizz(module.clone());
             return Some(FullModule {
                 id: module.id,
@@ -452,7 +626,7 @@ impl Contract {
                 created_at: module.created_at,
                 updated_at: module.updated_at,
                 lessons,
-                quizz: quizz,
+                quizz,
             });
         }
 
@@ -464,25 +638,19 @@ impl Contract {
         quizz_id: u64,
         student_id: AccountId,
     ) -> Option<FullQuizzProgress> {
-        for quizz_progress in self.quizz_progresses.iter() {
-            if quizz_progress.quizz_id == quizz_id && quizz_progress.student_id == student_id {
-                let quizz = self.get_quizz_by_id(quizz_id);
-                if let Some(quizz) = quizz {
-                    return Some(FullQuizzProgress {
-                        id: quizz_progress.id,
-                        quizz: quizz.clone(),
-                        student: self.get_user_by_id(student_id).unwrap(),
-                        status: quizz_progress.status.clone(),
-                        is_enrolled: quizz_progress.is_enrolled,
-                        try_count: quizz_progress.try_count,
-                        is_submitted: quizz_progress.is_submitted,
-                        is_correct: quizz_progress.is_correct,
-                        completed_at: quizz_progress.completed_at,
-                    });
-                }
-            }
-        }
-        None
+        let quizz_progress = self.get_quizz_progress(quizz_id, student_id.clone())?;
+        let quizz = self.get_quizz_by_id(quizz_id)?;
+        Some(FullQuizzProgress {
+            id: quizz_progress.id,
+            quizz,
+            student: self.get_user_by_id(student_id).unwrap(),
+            status: quizz_progress.status,
+            is_enrolled: quizz_progress.is_enrolled,
+            try_count: quizz_progress.try_count,
+            is_submitted: quizz_progress.is_submitted,
+            is_correct: quizz_progress.is_correct,
+            completed_at: quizz_progress.completed_at,
+        })
     }
 
     pub fn get_student_enrolled_course(
@@ -490,69 +658,67 @@ impl Contract {
         course_id: u64,
         student_id: AccountId,
     ) -> Option<FullEnrollment> {
-        for enrollment in self.enrollments.iter() {
-            if enrollment.course_id == course_id && enrollment.student_id == student_id.clone() {
-                let course = self.get_course_by_id(course_id);
-                if let Some(course) = course {
-                    let student = self.get_user_by_id(student_id.clone());
-                    if let Some(student) = student {
-                        let mut modules: Vec<FullModuleProgress> = vec![];
-                        for module_id in course.modules_ids.iter() {
-                            let module = self.get_full_module_by_id(*module_id);
-                            if let Some(module) = module {
-                                let module_progress =
-                                    self.get_module_progress(module.id, student_id.clone());
-                                if let Some(module_progress) = module_progress {
-                                    let mut lessons: Vec<FullLessonProgress> = vec![];
-                                    let quizz = module.clone().quizz;
-                                    let mut quizz_progress = None;
-                                    if let Some(quizz) = quizz {
-                                        quizz_progress = self
-                                            .get_full_quizz_progress(quizz.id, student_id.clone());
-                                    }
-                                    for lesson in module.lessons.iter() {
-                                        let lesson_progress =
-                                            self.get_lesson_progress(lesson.id, student_id.clone());
-                                        if let Some(lesson_progress) = lesson_progress {
-                                            lessons.push(FullLessonProgress {
-                                                id: lesson.id,
-                                                lesson: lesson.clone(),
-                                                student: student.clone(),
-                                                status: lesson_progress.status,
-                                                is_enrolled: lesson_progress.is_enrolled,
-                                                completed_at: lesson_progress.completed_at,
-                                            });
-                                        }
+        if let Some(enrollment) = self.get_enrollment(course_id, student_id.clone()) {
+            let course = self.get_course_by_id(course_id);
+            if let Some(course) = course {
+                let student = self.get_user_by_id(student_id.clone());
+                if let Some(student) = student {
+                    let mut modules: Vec<FullModuleProgress> = vec![];
+                    for module_id in course.modules_ids.iter() {
+                        let module = self.get_full_module_by_id(*module_id);
+                        if let Some(module) = module {
+                            let module_progress =
+                                self.get_module_progress(module.id, student_id.clone());
+                            if let Some(module_progress) = module_progress {
+                                let mut lessons: Vec<FullLessonProgress> = vec![];
+                                let quizz = module.clone().quizz;
+                                let mut quizz_progress = None;
+                                if let Some(quizz) = quizz {
+                                    quizz_progress =
+                                        self.get_full_quizz_progress(quizz.id, student_id.clone());
+                                }
+                                for lesson in module.lessons.iter() {
+                                    let lesson_progress =
+                                        self.get_lesson_progress(lesson.id, student_id.clone());
+                                    if let Some(lesson_progress) = lesson_progress {
+                                        lessons.push(FullLessonProgress {
+                                            id: lesson.id,
+                                            lesson: lesson.clone(),
+                                            student: student.clone(),
+                                            status: lesson_progress.status,
+                                            is_enrolled: lesson_progress.is_enrolled,
+                                            completed_at: lesson_progress.completed_at,
+                                        });
                                     }
-                                    modules.push(FullModuleProgress {
-                                        id: module_progress.id,
-                                        module: module.clone(),
-                                        student: student.clone(),
-                                        lessons,
-                                        quizz: quizz_progress,
-                                        status: module_progress.status,
-                                        is_enrolled: module_progress.is_enrolled,
-                                        progress: module_progress.progress,
-                                        completed_at: module_progress.completed_at,
-                                    });
                                 }
+                                modules.push(FullModuleProgress {
+                                    id: module_progress.id,
+                                    module: module.clone(),
+                                    student: student.clone(),
+                                    lessons,
+                                    quizz: quizz_progress,
+                                    status: module_progress.status,
+                                    is_enrolled: module_progress.is_enrolled,
+                                    progress: module_progress.progress,
+                                    completed_at: module_progress.completed_at,
+                                });
                             }
                         }
-
-                        return Some(FullEnrollment {
-                            id: enrollment.id,
-                            course: course.clone(),
-                            student: student.clone(),
-                            modules,
-                            status: enrollment.status.clone(),
-                            progress: enrollment.progress,
-                            carted_at: enrollment.carted_at,
-                            enrolled_at: enrollment.enrolled_at,
-                            completed_at: enrollment.completed_at,
-                            course_review: enrollment.course_review,
-                            updated_at: enrollment.updated_at,
-                        });
                     }
+
+                    return Some(FullEnrollment {
+                        id: enrollment.id,
+                        course: course.clone(),
+                        student: student.clone(),
+                        modules,
+                        status: enrollment.status,
+                        progress: enrollment.progress,
+                        carted_at: enrollment.carted_at,
+                        enrolled_at: enrollment.enrolled_at,
+                        completed_at: enrollment.completed_at,
+                        course_review: enrollment.course_review,
+                        updated_at: enrollment.updated_at,
+                    });
                 }
             }
         }
@@ -564,28 +730,32 @@ impl Contract {
         quizz_id: u64,
         student_id: AccountId,
     ) -> Option<QuizzProgress> {
-        for quizz_progress in self.quizz_progresses.iter() {
-            if quizz_progress.quizz_id == quizz_id && quizz_progress.student_id == student_id {
-                return Some(quizz_progress.clone());
-            }
-        }
-        None
+        self.quizz_progresses.get(&(quizz_id, student_id)).cloned()
     }
 
     pub fn get_user_created_courses(&self, account_id: AccountId) -> Vec<Course> {
         let mut course_list: Vec<Course> = vec![];
-        for course in self.courses.iter() {
-            if course.mentor_id == account_id {
-                course_list.push(course.clone());
+        if let Some(course_ids) = self.mentor_courses.get(&account_id) {
+            for course_id in course_ids.iter() {
+                if let Some(course) = self.get_course_by_id(*course_id) {
+                    course_list.push(course);
+                }
             }
         }
         course_list
     }
 
-    pub fn get_user_created_full_courses(&self, account_id: AccountId) -> Vec<FullCourse> {
+    pub fn get_user_created_full_courses(
+        &self,
+        account_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<FullCourse> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_FULL_COURSES_PAGE_LIMIT);
+
         let mut full_course_list: Vec<FullCourse> = vec![];
         let created_courses = self.get_user_created_courses(account_id);
-        for course in created_courses.iter() {
+        for course in created_courses.iter().skip(from_index).take(limit) {
             let full_course = self.get_full_course(course.id);
             if let Some(full_course) = full_course {
                 full_course_list.push(full_course);
@@ -593,4 +763,133 @@ impl Contract {
         }
         full_course_list
     }
+
+    /// Checks that every id referenced from a course, a module or a progress
+    /// row has a record, for the ids in `[from_index, from_index + limit)` of
+    /// the course, module, lesson and quizz id spaces. Progress maps cannot be
+    /// iterated, so rows are looked up for every id of the range that has no
+    /// record, for the students of the enrollments in
+    /// `[from_enrollment, from_enrollment + enrollment_limit)`. A full audit
+    /// walks both ranges up to the id counts.
+    pub fn audit_integrity(
+        &self,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+        from_enrollment: Option<u64>,
+        enrollment_limit: Option<u64>,
+    ) -> IntegrityReport {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_AUDIT_PAGE_LIMIT);
+        let ids = from_index as u64..(from_index + limit) as u64;
+        let (from_enrollment, enrollment_limit) =
+            page_bounds(from_enrollment, enrollment_limit, MAX_AUDIT_PAGE_LIMIT);
+        let enrollment_ids = from_enrollment as u64..(from_enrollment + enrollment_limit) as u64;
+
+        let mut report = IntegrityReport::default();
+
+        for course in ids.clone().filter_map(|id| self.courses.get(&id)) {
+            for module_id in course.modules_ids.iter() {
+                if !self.modules.contains_key(module_id) {
+                    report.dangling_course_modules.push((course.id, *module_id));
+                }
+            }
+        }
+
+        for module in ids.clone().filter_map(|id| self.modules.get(&id)) {
+            for lesson_id in module.lessons_ids.iter() {
+                if !self.lessons.contains_key(lesson_id) {
+                    report.dangling_module_lessons.push((module.id, *lesson_id));
+                }
+            }
+            if let Some(quizz_id) = module.quizz_id {
+                if !self.quizzes.contains_key(&quizz_id) {
+                    report.dangling_module_quizzes.push((module.id, quizz_id));
+                }
+            }
+        }
+
+        let mut students: BTreeSet<AccountId> = BTreeSet::new();
+        for enrollment_id in enrollment_ids.take_while(|id| *id < self.enrollment_count) {
+            if let Some(enrollment) = self.enrollments.get(&enrollment_id) {
+                students.insert(enrollment.student_id.clone());
+            }
+        }
+
+        for module_id in ids
+            .clone()
+            .take_while(|id| *id < self.module_count)
+            .filter(|id| !self.modules.contains_key(id))
+        {
+            for student_id in students.iter() {
+                if self
+                    .module_progresses
+                    .contains_key(&(module_id, student_id.clone()))
+                {
+                    report
+                        .dangling_module_progresses
+                        .push((module_id, student_id.clone()));
+                }
+            }
+        }
+
+        for lesson_id in ids
+            .clone()
+            .take_while(|id| *id < self.lesson_count)
+            .filter(|id| !self.lessons.contains_key(id))
+        {
+            for student_id in students.iter() {
+                if self
+                    .lesson_progresses
+                    .contains_key(&(lesson_id, student_id.clone()))
+                {
+                    report
+                        .dangling_lesson_progresses
+                        .push((lesson_id, student_id.clone()));
+                }
+            }
+        }
+
+        for quizz_id in ids
+            .take_while(|id| *id < self.quizz_count)
+            .filter(|id| !self.quizzes.contains_key(id))
+        {
+            for student_id in students.iter() {
+                if self
+                    .quizz_progresses
+                    .contains_key(&(quizz_id, student_id.clone()))
+                {
+                    report
+                        .dangling_quizz_progresses
+                        .push((quizz_id, student_id.clone()));
+                }
+            }
+        }
+
+        report.is_consistent = report.dangling_course_modules.is_empty()
+            && report.dangling_module_lessons.is_empty()
+            && report.dangling_module_quizzes.is_empty()
+            && report.dangling_module_progresses.is_empty()
+            && report.dangling_lesson_progresses.is_empty()
+            && report.dangling_quizz_progresses.is_empty();
+
+        report
+    }
+}
+
+impl Contract {
+    /// Full quizz including the correct answers, for grading and mentor edits only.
+    pub(crate) fn internal_get_quizz(&self, quizz_id: u64) -> Option<Quizz> {
+        self.quizzes.get(&quizz_id).cloned()
+    }
+}
+
+/// Turns the optional `from_index` and `limit` of a list getter into the
+/// number of entries to skip and take, capping the page at `max_limit`.
+pub(crate) fn page_bounds(
+    from_index: Option<u64>,
+    limit: Option<u64>,
+    max_limit: u64,
+) -> (usize, usize) {
+    let from_index = from_index.unwrap_or(0) as usize;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(max_limit) as usize;
+    (from_index, limit)
 }
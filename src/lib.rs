@@ -1,14 +1,35 @@
+// entrypoints take every field as a named JSON argument, and the
+// `#[near_bindgen]` generated `ContractExt` methods mirror their signatures
+#![allow(clippy::too_many_arguments)]
+
+use errors::ContractError;
+use events::*;
 use models::*;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
 use near_sdk::env::{self, log_str};
 use near_sdk::json_types::U128;
-use near_sdk::store::Vector;
+use near_sdk::store::{LookupMap, UnorderedMap, UnorderedSet};
 use near_sdk::{log, near_bindgen, AccountId, Promise};
 
 mod admin;
+mod certificates;
+mod earnings;
+mod errors;
+mod events;
 mod getters;
+mod migrations;
 mod models;
+mod nft;
+mod progress;
+mod refunds;
+mod reviews;
+mod roles;
+mod storage;
+mod tokens;
+mod treasury;
+#[cfg(test)]
 mod unit_tests;
+mod upgrade;
 mod utils;
 
 // Define the contract structure
@@ -16,15 +37,50 @@ mod utils;
 #[derive(BorshDeserialize, BorshSerialize)]
 pub struct Contract {
     pub owner_id: AccountId,
-    pub users: Vector<User>,
-    pub courses: Vector<Course>,
-    pub modules: Vector<Module>,
-    pub lessons: Vector<Lesson>,
-    pub enrollments: Vector<Enrollment>,
-    pub quizzes: Vector<Quizz>,
-    pub module_progresses: Vector<ModuleProgress>,
-    pub lesson_progresses: Vector<LessonProgress>,
-    pub quizz_progresses: Vector<QuizzProgress>,
+    pub roles: UnorderedMap<AccountId, Vec<Role>>,
+    pub storage_accounts: LookupMap<AccountId, StorageAccount>,
+    pub pending_upgrade: Option<PendingUpgrade>,
+    pub migration: Option<migrations::PendingMigrationV0>,
+    pub fee_bps: u16,
+    pub treasury_balance: u128,
+    pub course_fees: LookupMap<u64, u128>,
+    pub mentor_fees: LookupMap<AccountId, u128>,
+    pub mentor_earnings: LookupMap<AccountId, MentorEarnings>,
+    pub payouts: LookupMap<u64, Payout>,
+    pub mentor_payouts: LookupMap<AccountId, Vec<u64>>,
+    pub payout_count: u64,
+    pub accepted_tokens: UnorderedSet<AccountId>,
+    pub course_token_prices: LookupMap<(u64, AccountId), u128>,
+    pub token_earnings: LookupMap<(AccountId, AccountId), MentorEarnings>,
+    pub token_treasury: LookupMap<AccountId, u128>,
+    pub certificates: UnorderedMap<u64, Certificate>,
+    pub enrollment_certificates: LookupMap<u64, u64>,
+    pub student_certificates: LookupMap<AccountId, Vec<u64>>,
+    pub certificate_count: u64,
+    pub revoked_certificate_count: u64,
+    pub reviews: LookupMap<u64, Review>,
+    pub course_reviews: LookupMap<u64, Vec<u64>>,
+    pub student_reviews: LookupMap<(u64, AccountId), u64>,
+    pub review_count: u64,
+    pub refund_policy: RefundPolicy,
+    pub enrollment_payments: LookupMap<u64, EnrollmentPayment>,
+    pub mentor_escrows: LookupMap<AccountId, Vec<u64>>,
+    pub course_escrow_policies: LookupMap<u64, CourseEscrowPolicy>,
+    pub users: UnorderedMap<AccountId, User>,
+    pub usernames: LookupMap<String, AccountId>,
+    pub emails: LookupMap<String, AccountId>,
+    pub courses: UnorderedMap<u64, Course>,
+    pub mentor_courses: LookupMap<AccountId, Vec<u64>>,
+    pub modules: UnorderedMap<u64, Module>,
+    pub lessons: UnorderedMap<u64, Lesson>,
+    pub enrollments: LookupMap<u64, Enrollment>,
+    pub enrollment_ids: LookupMap<(u64, AccountId), u64>,
+    pub student_enrollments: LookupMap<AccountId, Vec<u64>>,
+    pub course_enrollments: LookupMap<u64, Vec<u64>>,
+    pub quizzes: UnorderedMap<u64, Quizz>,
+    pub module_progresses: LookupMap<(u64, AccountId), ModuleProgress>,
+    pub lesson_progresses: LookupMap<(u64, AccountId), LessonProgress>,
+    pub quizz_progresses: LookupMap<(u64, AccountId), QuizzProgress>,
     pub user_count: u64,
     pub course_count: u64,
     pub module_count: u64,
@@ -42,15 +98,53 @@ impl Default for Contract {
     fn default() -> Self {
         Self {
             owner_id: env::current_account_id(),
-            users: Vector::new(b"u".to_vec()),
-            courses: Vector::new(b"c".to_vec()),
-            modules: Vector::new(b"m".to_vec()),
-            lessons: Vector::new(b"l".to_vec()),
-            enrollments: Vector::new(b"e".to_vec()),
-            quizzes: Vector::new(b"q".to_vec()),
-            module_progresses: Vector::new(b"mp".to_vec()),
-            lesson_progresses: Vector::new(b"lp".to_vec()),
-            quizz_progresses: Vector::new(b"qp".to_vec()),
+            roles: UnorderedMap::new(b"r".to_vec()),
+            storage_accounts: LookupMap::new(b"s".to_vec()),
+            pending_upgrade: None,
+            migration: None,
+            fee_bps: treasury::DEFAULT_FEE_BPS,
+            treasury_balance: 0,
+            course_fees: LookupMap::new(b"cf".to_vec()),
+            mentor_fees: LookupMap::new(b"mf".to_vec()),
+            mentor_earnings: LookupMap::new(b"me".to_vec()),
+            payouts: LookupMap::new(b"po".to_vec()),
+            mentor_payouts: LookupMap::new(b"mp".to_vec()),
+            payout_count: 0,
+            accepted_tokens: UnorderedSet::new(b"at".to_vec()),
+            course_token_prices: LookupMap::new(b"tp".to_vec()),
+            token_earnings: LookupMap::new(b"te".to_vec()),
+            token_treasury: LookupMap::new(b"tt".to_vec()),
+            certificates: UnorderedMap::new(b"ct".to_vec()),
+            enrollment_certificates: LookupMap::new(b"ec".to_vec()),
+            student_certificates: LookupMap::new(b"sc".to_vec()),
+            certificate_count: 0,
+            revoked_certificate_count: 0,
+            reviews: LookupMap::new(b"rv".to_vec()),
+            course_reviews: LookupMap::new(b"cr".to_vec()),
+            student_reviews: LookupMap::new(b"sr".to_vec()),
+            review_count: 0,
+            refund_policy: RefundPolicy {
+                window_seconds: refunds::DEFAULT_REFUND_WINDOW_SECONDS,
+                max_progress: refunds::DEFAULT_REFUND_MAX_PROGRESS,
+            },
+            enrollment_payments: LookupMap::new(b"ep".to_vec()),
+            mentor_escrows: LookupMap::new(b"mx".to_vec()),
+            course_escrow_policies: LookupMap::new(b"xp".to_vec()),
+            users: UnorderedMap::new(b"ua".to_vec()),
+            usernames: LookupMap::new(b"un".to_vec()),
+            emails: LookupMap::new(b"ue".to_vec()),
+            courses: UnorderedMap::new(b"cs".to_vec()),
+            mentor_courses: LookupMap::new(b"mc".to_vec()),
+            modules: UnorderedMap::new(b"ms".to_vec()),
+            lessons: UnorderedMap::new(b"ls".to_vec()),
+            enrollments: LookupMap::new(b"ee".to_vec()),
+            enrollment_ids: LookupMap::new(b"ei".to_vec()),
+            student_enrollments: LookupMap::new(b"se".to_vec()),
+            course_enrollments: LookupMap::new(b"ce".to_vec()),
+            quizzes: UnorderedMap::new(b"qs".to_vec()),
+            module_progresses: LookupMap::new(b"mpa".to_vec()),
+            lesson_progresses: LookupMap::new(b"lpa".to_vec()),
+            quizz_progresses: LookupMap::new(b"qpa".to_vec()),
             user_count: 0,
             course_count: 0,
             module_count: 0,
@@ -78,6 +172,7 @@ impl Contract {
         self.test_count
     }
 
+    #[handle_result]
     pub fn create_user(
         &mut self,
         name: String,
@@ -87,24 +182,28 @@ impl Contract {
         by_google: bool,
 
         picture: String,
-        created_at: u64,
-    ) -> bool {
+        client_created_at: Option<u64>,
+    ) -> Result<bool, ContractError> {
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // Check if the user already exists
         if self.user_exists(account_id.clone(), username.clone(), email.clone()) {
-            return false;
+            return Err(ContractError::AlreadyExists(
+                "User already exists".to_string(),
+            ));
         }
 
-        log_str(&format!("Creating New User..."));
+        log_str("Creating New User...");
 
         let new_user: User = User {
-            account_id,
+            account_id: account_id.clone(),
             name,
             username: username.clone(),
             email,
             phone,
-            role: "user".to_string(), // default role is "user
+            role: UserRole::User,
 
             by_google,
 
@@ -113,10 +212,11 @@ impl Contract {
             picture,
             created_at,
             updated_at: created_at,
+            client_created_at,
         };
 
-        // add user to the users vector
-        self.users.push(new_user);
+        // add user to the users map and its indexes
+        self.internal_save_user(new_user);
 
         // Increment the user count
         self.user_count += 1;
@@ -124,10 +224,23 @@ impl Contract {
         // Log the creation of the user
         log_str(&format!("User created: {}", username));
 
-        true
+        // emit the user_created event
+        CourchainEvent::UserCreated(vec![UserCreatedData {
+            account_id: account_id.clone(),
+            username,
+            role: UserRole::User,
+            created_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
     // update user
+    #[handle_result]
     pub fn update_user(
         &mut self,
         account_id: AccountId,
@@ -139,24 +252,31 @@ impl Contract {
         by_google: bool,
 
         picture: String,
-        updated_at: u64,
-    ) -> bool {
+    ) -> Result<bool, ContractError> {
         let current_user = env::signer_account_id();
+        let mut initial_storage = env::storage_usage();
 
         // check if the user exists
         let user = self.get_user_by_id(account_id.clone());
         if user.is_none() {
-            log_str("User does not exist");
-            return false;
+            return Err(ContractError::NotFound("User does not exist".to_string()));
         }
 
         // check if the user is the current user or an admin
         if current_user != account_id.clone() && !self.is_admin(current_user.clone()) {
-            log_str("Only the user or admin can update the profile");
-            return false;
+            return Err(ContractError::Unauthorized(
+                "Only the user or admin can update the profile".to_string(),
+            ));
         }
 
-        log_str(&format!("Updating User..."));
+        // check if the new username or email belong to another user
+        if self.is_username_or_email_taken(&account_id, &username, &email) {
+            return Err(ContractError::AlreadyExists(
+                "Username or email is already taken".to_string(),
+            ));
+        }
+
+        log_str("Updating User...");
 
         // update the user
         let mut user = user.unwrap();
@@ -168,69 +288,83 @@ impl Contract {
         user.by_google = by_google;
 
         user.picture = picture;
-        user.updated_at = updated_at;
-
-        // get the index of the user in the users vector
-        let index = self
-            .users
-            .iter()
-            .position(|u| u.account_id == account_id)
-            .unwrap();
+        user.updated_at = env::block_timestamp();
 
-        // update the user
-        self.users.replace(index as u32, user.clone());
+        // update the user, a shorter profile frees storage its owner paid for
+        let owner_storage = self.storage_checkpoint();
+        self.internal_save_user(user.clone());
+        initial_storage = self.release_storage(&account_id, owner_storage, initial_storage);
 
         // Log the update of the user
         log_str(&format!("User updated: {}", username));
 
-        true
+        // emit the user_updated event
+        CourchainEvent::UserUpdated(vec![UserUpdatedData {
+            account_id: user.account_id.clone(),
+            username: user.username.clone(),
+            name: user.name.clone(),
+            role: user.role,
+            updated_by: current_user.clone(),
+            updated_at: user.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&current_user, initial_storage);
+
+        Ok(true)
     }
 
-
+    #[handle_result]
     pub fn update_user_info(
         &mut self,
         name: String,
         phone: String,
         picture: String,
-        updated_at: u64,
-    ) -> bool {
+    ) -> Result<bool, ContractError> {
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the user exists
         let user = self.get_user_by_id(account_id.clone());
         if user.is_none() {
-            log_str("User does not exist");
-            return false;
+            return Err(ContractError::NotFound("User does not exist".to_string()));
         }
 
-        log_str(&format!("Updating User Info..."));
+        log_str("Updating User Info...");
 
         // update the user
         let mut user = user.unwrap();
         user.name = name.clone();
         user.phone = phone;
         user.picture = picture;
-        user.updated_at = updated_at;
-
-        // get the index of the user in the users vector
-        let index = self
-            .users
-            .iter()
-            .position(|u| u.account_id == account_id)
-            .unwrap();
+        user.updated_at = env::block_timestamp();
 
         // update the user
-        self.users.replace(index as u32, user.clone());
+        self.internal_save_user(user.clone());
 
         // Log the update of the user info
         log_str(&format!("User info updated: {}", name));
 
-        true
+        // emit the user_updated event
+        CourchainEvent::UserUpdated(vec![UserUpdatedData {
+            account_id: user.account_id.clone(),
+            username: user.username.clone(),
+            name: user.name.clone(),
+            role: user.role,
+            updated_by: account_id.clone(),
+            updated_at: user.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-
-
     // create course
+    #[handle_result]
     pub fn create_course(
         &mut self,
         title: String,
@@ -243,18 +377,19 @@ impl Contract {
         picture: String,
         with_ai: bool,
         price: U128,
-        created_at: u64,
-    ) -> Course {
-        let account_id: AccountId = env::signer_account_id();
+        client_created_at: Option<u64>,
+    ) -> Result<Course, ContractError> {
+        let account_id = self.require_role(Role::Mentor, "Only mentors can create courses")?;
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the mentor exists
         let mentor = self.get_user_by_id(account_id.clone());
         if mentor.is_none() {
-            log_str("Mentor does not exist");
-            env::panic_str("Mentor does not exist")
+            return Err(ContractError::NotFound("Mentor does not exist".to_string()));
         }
 
-        log_str(&format!("Creating New Course..."));
+        log_str("Creating New Course...");
 
         let new_course: Course = Course {
             id: self.course_count,
@@ -262,7 +397,7 @@ impl Contract {
             description,
             level,
             duration,
-            status: "draft".to_string(),
+            status: CourseStatus::Draft,
             category,
             requirements,
             objectives,
@@ -271,12 +406,22 @@ impl Contract {
             price: u128::from(price),
             created_at,
             updated_at: created_at,
+            client_created_at,
             mentor_id: account_id.clone(),
             modules_ids: vec![],
+            rating_count: 0,
+            rating_sum: 0,
+            average_rating: 0.0,
         };
 
-        // add course to the courses vector
-        self.courses.push(new_course.clone());
+        // add course to the courses map
+        self.courses.insert(new_course.id, new_course.clone());
+
+        // index the course under its mentor
+        self.mentor_courses
+            .entry(account_id.clone())
+            .or_default()
+            .push(new_course.id);
 
         // Increment the course count
         self.course_count += 1;
@@ -284,37 +429,58 @@ impl Contract {
         // Log the creation of the course
         log_str(&format!("Course created: {} by {}", title, account_id));
 
-        return new_course;
+        // emit the course_created event
+        CourchainEvent::CourseCreated(vec![CourseCreatedData {
+            course_id: new_course.id,
+            mentor_id: account_id.clone(),
+            title,
+            price,
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(new_course)
     }
 
+    #[handle_result]
     pub fn create_module(
         &mut self,
         course_id: u64,
         title: String,
         description: String,
-        status: String,
+        status: ModuleStatus,
         order: u64,
         with_ai: bool,
-        created_at: u64,
-    ) -> bool {
+        client_created_at: Option<u64>,
+    ) -> Result<bool, ContractError> {
         // only mentors can create modules
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the mentor exists
         let mentor = self.get_user_by_id(account_id.clone());
         if mentor.is_none() {
-            log_str("Mentor does not exist");
-            return false;
+            return Err(ContractError::NotFound("Mentor does not exist".to_string()));
         }
 
         // check if the course exists
         let course = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            return false;
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
-        log_str(&format!("Creating New Module..."));
+        // check if the mentor is the mentor of the course
+        self.require_course_mentor(
+            course.as_ref().unwrap(),
+            "Only the mentor of the course can create modules",
+        )?;
+
+        log_str("Creating New Module...");
 
         let new_module: Module = Module {
             id: self.module_count,
@@ -326,12 +492,13 @@ impl Contract {
             with_ai,
             created_at,
             updated_at: created_at,
+            client_created_at,
             lessons_ids: vec![],
             quizz_id: None,
         };
 
-        // add module to the modules vector
-        self.modules.push(new_module);
+        // add module to the modules map
+        self.modules.insert(new_module.id, new_module);
 
         // Log the creation of the module
         log_str(&format!("Module created: {} by {}", title, account_id));
@@ -341,76 +508,109 @@ impl Contract {
         course.modules_ids.push(self.module_count);
 
         // update the course
-        self.courses.replace(course_id as u32, course.clone());
+        self.courses.insert(course_id, course.clone());
 
         // Log the addition of the module to the course
         log_str(&format!("Module added to course: {}", course.title));
 
+        // emit the module_created event
+        CourchainEvent::ModuleCreated(vec![ModuleCreatedData {
+            module_id: self.module_count,
+            course_id,
+            title,
+            order,
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
         // Increment the module count
         self.module_count += 1;
 
-        return true;
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
+    #[handle_result]
     pub fn update_module(
         &mut self,
         module_id: u64,
         title: String,
         description: String,
-        updated_at: u64,
-    ) {
+    ) -> Result<(), ContractError> {
         // Only the mentor of the module can update the module details
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the module exists
         let module: Option<Module> = self.get_module_by_id(module_id);
         if module.is_none() {
-            log_str("Module does not exist");
-            env::panic_str("Module does not exist")
+            return Err(ContractError::NotFound("Module does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the module
         let module: Module = module.unwrap();
         let course: Course = self.get_course_by_id(module.course_id).unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the module can update the module details");
-            env::panic_str("Only the mentor of the module can update the module details")
-        }
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the module can update the module details",
+        )?;
 
         // update the module details
         let mut module: Module = module.clone();
         module.title = title;
         module.description = description;
-        module.updated_at = updated_at;
-
-        // get the index of the module in the modules vector
-        let index: usize = self.modules.iter().position(|m| m.id == module.id).unwrap();
+        module.updated_at = env::block_timestamp();
 
         // update the module
-        self.modules.replace(index as u32, module.clone());
+        self.modules.insert(module.id, module.clone());
 
         // Log the update of the module details
         log_str(&format!("Module details updated: {}", module.title));
+
+        // emit the module_updated event
+        CourchainEvent::ModuleUpdated(vec![ModuleUpdatedData {
+            module_id: module.id,
+            title: module.title.clone(),
+            updated_by: account_id.clone(),
+            updated_at: module.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
     }
 
+    #[handle_result]
     pub fn create_quizz(
         &mut self,
         module_id: u64,
         title: String,
         description: String,
-        created_at: u64,
-    ) -> bool {
+        client_created_at: Option<u64>,
+    ) -> Result<bool, ContractError> {
         // only mentors can create quizzes
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the module exists
-        let module: Option<Module> = self.get_module_by_id(module_id.clone());
+        let module: Option<Module> = self.get_module_by_id(module_id);
         if module.is_none() {
-            log_str("Module does not exist");
-            return false;
+            return Err(ContractError::NotFound("Module does not exist".to_string()));
         }
 
-        log_str(&format!("Creating New Quizz..."));
+        // check if the mentor is the mentor of the module
+        let course: Course = self
+            .get_course_by_id(module.as_ref().unwrap().course_id)
+            .unwrap();
+        self.require_course_mentor(&course, "Only the mentor of the module can create quizzes")?;
+
+        log_str("Creating New Quizz...");
 
         let new_quizz: Quizz = Quizz {
             id: self.quizz_count,
@@ -421,20 +621,31 @@ impl Contract {
             with_ai: false,
             created_at,
             updated_at: created_at,
+            client_created_at,
         };
 
-        // add quizz to the quizzes vector
-        self.quizzes.push(new_quizz);
+        // add quizz to the quizzes map
+        self.quizzes.insert(new_quizz.id, new_quizz);
 
         // Log the creation of the quizz
         log_str(&format!("Quizz created: {} by {}", title, account_id));
 
+        // emit the quiz_created event
+        CourchainEvent::QuizCreated(vec![QuizCreatedData {
+            quizz_id: self.quizz_count,
+            module_id,
+            title,
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
         // add the quizz id to the module
         let mut module = module.unwrap();
         module.quizz_id = Some(self.quizz_count);
 
         // update the module
-        self.modules.replace(module_id as u32, module.clone());
+        self.modules.insert(module_id, module.clone());
 
         // Log the addition of the quizz to the module
         log_str(&format!("Quizz added to module: {}", module.title));
@@ -442,44 +653,66 @@ impl Contract {
         // Increment the quizz count
         self.quizz_count += 1;
 
-        return true;
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-    pub fn save_quizz_questions(&mut self, quizz_id: u64, with_ai: bool, questions: Vec<Question>) {
+    #[handle_result]
+    pub fn save_quizz_questions(
+        &mut self,
+        quizz_id: u64,
+        with_ai: bool,
+        questions: Vec<Question>,
+    ) -> Result<(), ContractError> {
         // only mentors can save quizz questions
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the quizz exists
-        let quizz: Option<Quizz> = self.get_quizz_by_id(quizz_id);
+        let quizz: Option<Quizz> = self.internal_get_quizz(quizz_id);
         if quizz.is_none() {
-            log_str("Quizz does not exist");
-            env::panic_str("Quizz does not exist")
+            return Err(ContractError::NotFound("Quizz does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the quizz
         let quizz: Quizz = quizz.unwrap();
         let module: Module = self.get_module_by_id(quizz.module_id).unwrap();
         let course: Course = self.get_course_by_id(module.course_id).unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the quizz can save quizz questions");
-            env::panic_str("Only the mentor of the quizz can save quizz questions")
-        }
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the quizz can save quizz questions",
+        )?;
 
         // update the quizz questions
         let mut quizz: Quizz = quizz.clone();
         quizz.questions = questions;
         quizz.with_ai = with_ai;
 
-        // get the index of the quizz in the quizzes vector
-        let index: usize = self.quizzes.iter().position(|q| q.id == quizz.id).unwrap();
-
         // update the quizz
-        self.quizzes.replace(index as u32, quizz.clone());
+        self.quizzes.insert(quizz.id, quizz.clone());
 
         // Log the saving of the quizz questions
         log_str(&format!("Quizz questions saved: {}", quizz.title));
+
+        // emit the quiz_questions_saved event
+        CourchainEvent::QuizQuestionsSaved(vec![QuizQuestionsSavedData {
+            quizz_id: quizz.id,
+            module_id: quizz.module_id,
+            questions_count: quizz.questions.len() as u32,
+            with_ai: quizz.with_ai,
+            saved_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
     }
 
+    #[handle_result]
     pub fn create_lesson(
         &mut self,
         module_id: u64,
@@ -489,19 +722,26 @@ impl Contract {
         article: String,
         order: u64,
         with_ai: bool,
-        created_at: u64,
-    ) -> bool {
+        client_created_at: Option<u64>,
+    ) -> Result<bool, ContractError> {
         // only mentors can create lessons
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
 
         // check if the module exists
         let module = self.get_module_by_id(module_id);
         if module.is_none() {
-            log_str("Module does not exist");
-            return false;
+            return Err(ContractError::NotFound("Module does not exist".to_string()));
         }
 
-        log_str(&format!("Creating New Lesson..."));
+        // check if the mentor is the mentor of the module
+        let course: Course = self
+            .get_course_by_id(module.as_ref().unwrap().course_id)
+            .unwrap();
+        self.require_course_mentor(&course, "Only the mentor of the module can create lessons")?;
+
+        log_str("Creating New Lesson...");
 
         let new_lesson: Lesson = Lesson {
             id: self.lesson_count,
@@ -514,10 +754,11 @@ impl Contract {
             with_ai,
             created_at,
             updated_at: created_at,
+            client_created_at,
         };
 
-        // add lesson to the lessons vector
-        self.lessons.push(new_lesson);
+        // add lesson to the lessons map
+        self.lessons.insert(new_lesson.id, new_lesson);
 
         // Log the creation of the lesson
         log_str(&format!("Lesson created: {} by {}", title, account_id));
@@ -527,79 +768,112 @@ impl Contract {
         module.lessons_ids.push(self.lesson_count);
 
         // update the module
-        self.modules.replace(module_id as u32, module.clone());
+        self.modules.insert(module_id, module.clone());
 
         // Log the addition of the lesson to the module
         log_str(&format!("Lesson added to module: {}", module.title));
 
+        // emit the lesson_created event
+        CourchainEvent::LessonCreated(vec![LessonCreatedData {
+            lesson_id: self.lesson_count,
+            module_id,
+            title,
+            order,
+            created_by: account_id.clone(),
+            created_at,
+        }])
+        .emit();
+
         // Increment the lesson count
         self.lesson_count += 1;
 
-        return true;
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
+    #[handle_result]
     pub fn update_lesson_details(
         &mut self,
         lesson_id: u64,
         title: String,
         description: String,
-        updated_at: u64,
-    ) -> bool {
+    ) -> Result<bool, ContractError> {
         // Only the mentor of the lesson can update the lesson details
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the lesson exists
         let lesson: Option<Lesson> = self.get_lesson_by_id(lesson_id);
         if lesson.is_none() {
-            log_str("Lesson does not exist");
-            return false;
+            return Err(ContractError::NotFound("Lesson does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the lesson
         let lesson: Lesson = lesson.unwrap();
         let module: Module = self.get_module_by_id(lesson.module_id).unwrap();
         let course: Course = self.get_course_by_id(module.course_id).unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the lesson can update the lesson details");
-            return false;
-        }
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the lesson can update the lesson details",
+        )?;
 
         // update the lesson details
         let mut lesson: Lesson = lesson.clone();
         lesson.title = title;
         lesson.description = description;
-        lesson.updated_at = updated_at;
-
-        // get the index of the lesson in the lessons vector
-        let index: usize = self.lessons.iter().position(|l| l.id == lesson.id).unwrap();
+        lesson.updated_at = env::block_timestamp();
 
         // update the lesson
-        self.lessons.replace(index as u32, lesson.clone());
+        self.lessons.insert(lesson.id, lesson.clone());
 
         // Log the update of the lesson details
         log_str(&format!("Lesson details updated: {}", lesson.title));
 
-        true
+        // emit the lesson_updated event
+        CourchainEvent::LessonUpdated(vec![LessonUpdatedData {
+            lesson_id: lesson.id,
+            module_id: lesson.module_id,
+            title: lesson.title.clone(),
+            order: lesson.order,
+            updated_by: account_id.clone(),
+            updated_at: lesson.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-    pub fn delete_lesson(&mut self, lesson_id: u64) {
+    #[handle_result]
+    pub fn delete_lesson(&mut self, lesson_id: u64) -> Result<(), ContractError> {
         // Only the mentor of the lesson can delete the lesson
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the lesson exists
         let lesson: Option<Lesson> = self.get_lesson_by_id(lesson_id);
         if lesson.is_none() {
-            log_str("Lesson does not exist");
-            env::panic_str("Lesson does not exist")
+            return Err(ContractError::NotFound("Lesson does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the lesson
         let lesson: Lesson = lesson.unwrap();
         let mut module: Module = self.get_module_by_id(lesson.module_id).unwrap();
         let course: Course = self.get_course_by_id(module.course_id).unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the lesson can delete the lesson");
-            env::panic_str("Only the mentor of the lesson can delete the lesson")
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the lesson can delete the lesson",
+        )?;
+
+        // students' progress depends on the lesson
+        if self.has_active_enrollments(course.id) {
+            return Err(ContractError::InvalidState(
+                "Cannot delete the lesson of a course with enrolled students".to_string(),
+            ));
         }
 
         // remove the lesson from module lessons
@@ -619,141 +893,523 @@ impl Contract {
                 let mut lesson = lesson.clone();
                 lesson.order -= 1;
 
-                // get the index of the lesson in the lessons vector
-                let index: usize = self.lessons.iter().position(|l| l.id == lesson.id).unwrap();
+                // update the lesson
+                self.lessons.insert(lesson.id, lesson.clone());
+            }
+        }
+
+        // update the module
+        self.modules.insert(module.id, module.clone());
+
+        // remove the lesson and its progress rows
+        let students = self.internal_course_student_ids(course.id);
+        self.internal_remove_lesson(lesson_id, &students);
+
+        // Log the deletion of the lesson
+        log_str(&format!("Lesson deleted: {}", lesson.title));
+
+        // emit the lesson_deleted event
+        CourchainEvent::LessonDeleted(vec![LessonDeletedData {
+            lesson_id,
+            module_id: lesson.module_id,
+            deleted_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn reorder_module_lessons(
+        &mut self,
+        module_id: u64,
+        lessons_ids: Vec<u64>,
+    ) -> Result<(), ContractError> {
+        // Only the mentor of the module can reorder its lessons
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the module exists
+        let module: Option<Module> = self.get_module_by_id(module_id);
+        if module.is_none() {
+            return Err(ContractError::NotFound("Module does not exist".to_string()));
+        }
+
+        // check if the mentor is the mentor of the module
+        let mut module: Module = module.unwrap();
+        let course: Course = self.get_course_by_id(module.course_id).unwrap();
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the module can reorder its lessons",
+        )?;
+
+        // the new order must list every lesson of the module exactly once
+        if !is_permutation(&module.lessons_ids, &lessons_ids) {
+            return Err(ContractError::InvalidInput(
+                "Lessons ids must be a permutation of the module lessons".to_string(),
+            ));
+        }
+
+        // rewrite the lessons order from their position
+        let updated_at = env::block_timestamp();
+        for (index, lesson_id) in lessons_ids.iter().enumerate() {
+            if let Some(mut lesson) = self.get_lesson_by_id(*lesson_id) {
+                lesson.order = index as u64 + 1;
+                lesson.updated_at = updated_at;
 
                 // update the lesson
-                self.lessons.replace(index as u32, lesson.clone());
+                self.lessons.insert(lesson.id, lesson);
             }
         }
 
-        // get the index of the module in the modules vector
-        let mod_index = self.modules.iter().position(|m| m.id == module.id).unwrap();
+        // update the module
+        module.lessons_ids = lessons_ids.clone();
+        module.updated_at = updated_at;
+        self.modules.insert(module_id, module.clone());
+
+        // Log the reordering of the lessons
+        log_str(&format!("Lessons reordered in module: {}", module.title));
+
+        // emit the lessons_reordered event
+        CourchainEvent::LessonsReordered(vec![LessonsReorderedData {
+            module_id,
+            lessons_ids,
+            reordered_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn reorder_course_modules(
+        &mut self,
+        course_id: u64,
+        modules_ids: Vec<u64>,
+    ) -> Result<(), ContractError> {
+        // Only the mentor of the course can reorder its modules
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the course exists
+        let course: Option<Course> = self.get_course_by_id(course_id);
+        if course.is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+
+        // check if the mentor is the mentor of the course
+        let mut course: Course = course.unwrap();
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the course can reorder its modules",
+        )?;
+
+        // the new order must list every module of the course exactly once
+        if !is_permutation(&course.modules_ids, &modules_ids) {
+            return Err(ContractError::InvalidInput(
+                "Modules ids must be a permutation of the course modules".to_string(),
+            ));
+        }
+
+        // rewrite the modules order from their position
+        let updated_at = env::block_timestamp();
+        for (index, module_id) in modules_ids.iter().enumerate() {
+            if let Some(mut module) = self.get_module_by_id(*module_id) {
+                module.order = index as u64 + 1;
+                module.updated_at = updated_at;
+
+                // update the module
+                self.modules.insert(module.id, module);
+            }
+        }
+
+        // update the course
+        course.modules_ids = modules_ids.clone();
+        course.updated_at = updated_at;
+        self.courses.insert(course_id, course.clone());
+
+        // Log the reordering of the modules
+        log_str(&format!("Modules reordered in course: {}", course.title));
+
+        // emit the modules_reordered event
+        CourchainEvent::ModulesReordered(vec![ModulesReorderedData {
+            course_id,
+            modules_ids,
+            reordered_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn delete_quizz(&mut self, quizz_id: u64) -> Result<(), ContractError> {
+        // Only the mentor of the quizz can delete the quizz
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the quizz exists
+        let quizz: Option<Quizz> = self.internal_get_quizz(quizz_id);
+        if quizz.is_none() {
+            return Err(ContractError::NotFound("Quizz does not exist".to_string()));
+        }
+
+        // check if the mentor is the mentor of the quizz
+        let quizz: Quizz = quizz.unwrap();
+        let mut module: Module = self.get_module_by_id(quizz.module_id).unwrap();
+        let course: Course = self.get_course_by_id(module.course_id).unwrap();
+        self.require_course_mentor(&course, "Only the mentor of the quizz can delete the quizz")?;
+
+        // students' progress depends on the quizz
+        if self.has_active_enrollments(course.id) {
+            return Err(ContractError::InvalidState(
+                "Cannot delete the quizz of a course with enrolled students".to_string(),
+            ));
+        }
+
+        // unlink the quizz from its module
+        module.quizz_id = None;
 
         // update the module
-        self.modules.replace(mod_index as u32, module.clone());
+        self.modules.insert(module.id, module.clone());
 
-        // remove lesson from the lessons vector
-        let index = self.lessons.iter().position(|l| l.id == lesson_id).unwrap();
+        // remove the quizz and its progress rows
+        let students = self.internal_course_student_ids(course.id);
+        self.internal_remove_quizz(quizz_id, &students);
 
-        let _removed_lesson = self.lessons.swap_remove(index as u32);
+        // Log the deletion of the quizz
+        log_str(&format!("Quizz deleted: {}", quizz.title));
 
-        // Log the deletion of the lesson
-        log_str(&format!("Lesson deleted: {}", lesson.title));
+        // emit the quiz_deleted event
+        CourchainEvent::QuizDeleted(vec![QuizDeletedData {
+            quizz_id,
+            module_id: module.id,
+            deleted_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
     }
 
-    pub fn publish_course(&mut self, course_id: u64, published_at: u64) -> bool {
+    #[handle_result]
+    pub fn delete_module(&mut self, module_id: u64) -> Result<(), ContractError> {
+        // Only the mentor of the module can delete the module
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the module exists
+        let module: Option<Module> = self.get_module_by_id(module_id);
+        if module.is_none() {
+            return Err(ContractError::NotFound("Module does not exist".to_string()));
+        }
+
+        // check if the mentor is the mentor of the module
+        let module: Module = module.unwrap();
+        let mut course: Course = self.get_course_by_id(module.course_id).unwrap();
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the module can delete the module",
+        )?;
+
+        // students' progress depends on the module
+        if self.has_active_enrollments(course.id) {
+            return Err(ContractError::InvalidState(
+                "Cannot delete a module of a course with enrolled students".to_string(),
+            ));
+        }
+
+        // remove the module from course modules
+        course.modules_ids.retain(|m| m != &module_id);
+
+        // update modules order that have order higher than the module order in the course
+        for module_id in course.modules_ids.iter() {
+            let sibling = self.get_module_by_id(*module_id).unwrap();
+            if sibling.order > module.order {
+                let mut sibling = sibling.clone();
+                sibling.order -= 1;
+
+                // update the module
+                self.modules.insert(sibling.id, sibling.clone());
+            }
+        }
+
+        // update the course
+        self.courses.insert(course.id, course.clone());
+
+        // remove the module with its lessons, quizz and progress rows
+        let students = self.internal_course_student_ids(course.id);
+        self.internal_remove_module(&module, &students);
+
+        // Log the deletion of the module
+        log_str(&format!("Module deleted: {}", module.title));
+
+        // emit the module_deleted event
+        CourchainEvent::ModuleDeleted(vec![ModuleDeletedData {
+            module_id,
+            course_id: course.id,
+            lessons_ids: module.lessons_ids.clone(),
+            quizz_id: module.quizz_id,
+            deleted_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn delete_course(&mut self, course_id: u64) -> Result<(), ContractError> {
+        // Only the mentor of the course can delete the course
+        let account_id: AccountId = env::signer_account_id();
+        let mut initial_storage = env::storage_usage();
 
         // check if the course exists
-        let course = self.get_course_by_id(course_id);
+        let course: Option<Course> = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            return false;
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
-        // only the course mentor and admin can publish the course
-        if !self.is_course_mentor(course_id, account_id.clone())
-            && !self.is_admin(account_id.clone())
+        // check if the mentor is the mentor of the course
+        let course: Course = course.unwrap();
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the course can delete the course",
+        )?;
+
+        // courses with students are archived instead
+        if self.has_active_enrollments(course_id) {
+            return Err(ContractError::InvalidState(
+                "Cannot delete a course with enrolled students, archive it instead".to_string(),
+            ));
+        }
+
+        // remove the modules with their lessons, quizzes and progress rows
+        let students = self.internal_course_student_ids(course_id);
+        for module_id in course.modules_ids.iter() {
+            if let Some(module) = self.get_module_by_id(*module_id) {
+                self.internal_remove_module(&module, &students);
+            }
+        }
+
+        // drop the course from the carts it is in, refunded enrollments
+        // stay as the record of their payments
+        for enrollment in self.internal_course_enrollments(course_id).iter() {
+            if enrollment.status == EnrollmentStatus::Carted {
+                // the student paid for the enrollment, give them the storage back
+                let student_storage = self.storage_checkpoint();
+                self.internal_remove_enrollment(enrollment);
+                initial_storage =
+                    self.release_storage(&enrollment.student_id, student_storage, initial_storage);
+            }
+        }
+
+        // remove the reviews left on the course, releasing them to their authors
+        if let Some(review_ids) = self.course_reviews.remove(&course_id) {
+            for review_id in review_ids.iter() {
+                let student_storage = self.storage_checkpoint();
+                if let Some(review) = self.reviews.remove(review_id) {
+                    self.student_reviews
+                        .remove(&(course_id, review.student_id.clone()));
+                    initial_storage =
+                        self.release_storage(&review.student_id, student_storage, initial_storage);
+                }
+            }
+        }
+
+        // remove the course settings
+        for token_id in self
+            .accepted_tokens
+            .iter()
+            .cloned()
+            .collect::<Vec<AccountId>>()
         {
-            log_str("Only the course mentor or admin can publish the course");
-            return false;
+            self.course_token_prices.remove(&(course_id, token_id));
         }
+        self.course_escrow_policies.remove(&course_id);
 
-        // change the course status to published
+        // remove the course from the mentor index
+        if let Some(course_ids) = self.mentor_courses.get_mut(&course.mentor_id) {
+            course_ids.retain(|id| *id != course_id);
+        }
+
+        // remove course from the courses map
+        self.courses.remove(&course_id);
+
+        // Log the deletion of the course
+        log_str(&format!("Course deleted: {}", course.title));
+
+        // emit the course_deleted event
+        CourchainEvent::CourseDeleted(vec![CourseDeletedData {
+            course_id,
+            modules_ids: course.modules_ids.clone(),
+            deleted_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(())
+    }
+
+    #[handle_result]
+    pub fn publish_course(&mut self, course_id: u64) -> Result<bool, ContractError> {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the course exists
+        let course = self.get_course_by_id(course_id);
+        if course.is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+
+        // only the course mentor and admin can publish the course
         let mut course = course.unwrap();
-        course.status = "published".to_string();
-        course.updated_at = published_at;
+        self.require_course_mentor(
+            &course,
+            "Only the course mentor or admin can publish the course",
+        )?;
 
-        // get the index of the course in the courses vector
-        let index = self.courses.iter().position(|c| c.id == course.id).unwrap();
+        // change the course status to published
+        course.status.transition_to(CourseStatus::Published)?;
+        course.updated_at = env::block_timestamp();
 
         // update the course
-        self.courses.replace(index as u32, course.clone());
+        self.courses.insert(course.id, course.clone());
+
+        // emit the course_published event
+        CourchainEvent::CoursePublished(vec![CourseStatusData {
+            course_id,
+            status: course.status,
+            changed_by: account_id.clone(),
+        }])
+        .emit();
 
-        return true;
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-    pub fn archive_course(&mut self, course_id: u64) -> bool {
+    #[handle_result]
+    pub fn archive_course(&mut self, course_id: u64) -> Result<bool, ContractError> {
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the course exists
         let course = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            return false;
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
         // only the course mentor and admin can archive the course
-        if !self.is_course_mentor(course_id, account_id.clone())
-            && !self.is_admin(account_id.clone())
-        {
-            log_str("Only the course mentor or admin can archive the course");
-            return false;
-        }
-
-        // change the course status to archived
         let mut course = course.unwrap();
-        course.status = "archived".to_string();
+        self.require_course_mentor(
+            &course,
+            "Only the course mentor or admin can archive the course",
+        )?;
 
-        // get the index of the course in the courses vector
-        let index = self.courses.iter().position(|c| c.id == course.id).unwrap();
+        // change the course status to archived
+        course.status.transition_to(CourseStatus::Archived)?;
+        course.updated_at = env::block_timestamp();
 
         // update the course
-        self.courses.replace(index as u32, course.clone());
+        self.courses.insert(course.id, course.clone());
+
+        // emit the course_archived event
+        CourchainEvent::CourseArchived(vec![CourseStatusData {
+            course_id,
+            status: course.status,
+            changed_by: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
 
-        return true;
+        Ok(true)
     }
 
-    pub fn save_course_to_cart(&mut self, course_id: u64, carted_at: u64) -> bool {
+    #[handle_result]
+    pub fn save_course_to_cart(&mut self, course_id: u64) -> Result<bool, ContractError> {
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let carted_at = env::block_timestamp();
 
         // check if the course exists
         let course = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            return false;
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
         // check if the student exists
         let student = self.get_user_by_id(account_id.clone());
         if student.is_none() {
-            log_str("Student does not exist");
-            return false;
+            return Err(ContractError::NotFound(
+                "Student does not exist".to_string(),
+            ));
         }
 
         // check if the user is the mentor of that course
         if self.is_course_mentor(course_id, account_id.clone()) {
-            log_str("User cannot cart their own course");
-            return false;
+            return Err(ContractError::Unauthorized(
+                "User cannot cart their own course".to_string(),
+            ));
         }
 
         // check if the course is already enrolled ny user
         if self.is_student_course_enrolled(course_id, account_id.clone()) {
-            log_str("Student is already enrolled in the course");
-            return false;
+            return Err(ContractError::AlreadyExists(
+                "Student is already enrolled in the course".to_string(),
+            ));
         }
 
         // check if the course is already carted by user
         if self.is_student_course_carted(course_id, account_id.clone()) {
-            log_str("Course is already carted");
-            return false;
+            return Err(ContractError::AlreadyExists(
+                "Course is already carted".to_string(),
+            ));
         }
 
         // check if the course is already completed by user
         if self.is_student_course_completed(course_id, account_id.clone()) {
-            log_str("Course is already completed");
-            return false;
+            return Err(ContractError::InvalidState(
+                "Course is already completed".to_string(),
+            ));
+        }
+
+        // check if a refund of the course is still in flight
+        if self
+            .get_enrollment(course_id, account_id.clone())
+            .is_some_and(|enrollment| enrollment.status == EnrollmentStatus::Refunding)
+        {
+            return Err(ContractError::InvalidState(
+                "A refund of the course is in progress".to_string(),
+            ));
         }
 
-        log_str(&format!("Saving Course to Cart..."));
+        log_str("Saving Course to Cart...");
 
         // create new enrollment with status carted
         let new_enrollment: Enrollment = Enrollment {
             id: self.enrollment_count,
             course_id,
             student_id: account_id.clone(),
-            status: "carted".to_string(),
+            status: EnrollmentStatus::Carted,
             progress: 0,
             carted_at,
             enrolled_at: None,
@@ -762,8 +1418,8 @@ impl Contract {
             completed_at: None,
         };
 
-        // add enrollment to the enrollments vector
-        self.enrollments.push(new_enrollment);
+        // add enrollment to the enrollments map and its indexes
+        self.internal_add_enrollment(new_enrollment.clone());
 
         // Increment the enrollment count
         self.enrollment_count += 1;
@@ -774,43 +1430,54 @@ impl Contract {
             course.unwrap().title
         ));
 
-        true
+        // emit the course_carted event
+        CourchainEvent::CourseCarted(vec![CourseCartedData {
+            enrollment_id: new_enrollment.id,
+            course_id,
+            student_id: account_id.clone(),
+            carted_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-    pub fn remove_course_from_cart(&mut self, course_id: u64) -> bool {
+    #[handle_result]
+    pub fn remove_course_from_cart(&mut self, course_id: u64) -> Result<bool, ContractError> {
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the course exists
         let course = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            return false;
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
         // check if the student exists
         let student = self.get_user_by_id(account_id.clone());
         if student.is_none() {
-            log_str("Student does not exist");
-            return false;
+            return Err(ContractError::NotFound(
+                "Student does not exist".to_string(),
+            ));
         }
 
         // check if the course is already carted by user
         if !self.is_student_course_carted(course_id, account_id.clone()) {
-            log_str("Course is not carted");
-            return false;
+            return Err(ContractError::InvalidState(
+                "Course is not carted".to_string(),
+            ));
         }
 
-        log_str(&format!("Removing Course from Cart..."));
+        log_str("Removing Course from Cart...");
 
         // get the enrollment of the course by the student
-        let index = self
-            .enrollments
-            .iter()
-            .position(|e| e.course_id == course_id && e.student_id == account_id)
-            .unwrap();
+        let enrollment = self.get_enrollment(course_id, account_id.clone()).unwrap();
 
-        // remove the enrollment from the enrollments vector
-        let _removed_enrollment = self.enrollments.swap_remove(index as u32);
+        // remove the enrollment from the enrollments map and its indexes
+        self.internal_remove_enrollment(&enrollment);
 
         // Log the removal of the course from the cart
         log_str(&format!(
@@ -819,12 +1486,26 @@ impl Contract {
             account_id
         ));
 
-        true
+        // emit the course_removed_from_cart event
+        CourchainEvent::CourseRemovedFromCart(vec![CourseRemovedFromCartData {
+            enrollment_id: enrollment.id,
+            course_id,
+            student_id: account_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
     #[payable]
-    pub fn enroll_all_carted_courses(&mut self, enrolled_at: u64) {
+    #[handle_result]
+    pub fn enroll_all_carted_courses(&mut self) -> Result<EnrollmentReceipt, ContractError> {
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let enrolled_at = env::block_timestamp();
         // get attached deposit
         let attached_deposit = env::attached_deposit();
 
@@ -838,222 +1519,184 @@ impl Contract {
         log!(format!("Total courses price: {}", total_courses_price));
         log!(format!("Attached deposit: {}", attached_deposit));
 
-        // check if the attached deposit is equal to the total courses price
+        // check if the attached deposit covers the total courses price
         if attached_deposit < total_courses_price {
-            log_str("Attached deposit is not equal to the total courses price");
-            panic!("Attached deposit is not equal to the total courses price");
+            return Err(ContractError::InsufficientDeposit(
+                "Attached deposit is less than the total courses price".to_string(),
+            ));
         }
 
-        log_str(&format!("Enrolling Student in All Carted Courses..."));
+        let mut receipt_courses: Vec<EnrolledCourseReceipt> = vec![];
+
+        log_str("Enrolling Student in All Carted Courses...");
         // getting all carted courses enrollments
         let user_carted_enrollment: Vec<Enrollment> =
             self.get_user_carted_enrollments(account_id.clone());
 
         for carted_enrollment in user_carted_enrollment.iter() {
-            // update the enrollment status to enrolled
-            let mut enrollment: Enrollment = carted_enrollment.clone();
-
-            enrollment.status = "enrolled".to_string();
-            enrollment.enrolled_at = Some(enrolled_at);
-            enrollment.updated_at = enrolled_at;
-
-            // get enrollment index
-            let index = self
-                .enrollments
-                .iter()
-                .position(|e| e.id == enrollment.id)
-                .unwrap();
-
-            // update the enrollment
-            self.enrollments.replace(index as u32, enrollment.clone());
+            // enroll the student and create their progress rows
+            let course = self.internal_enroll(carted_enrollment, enrolled_at)?;
 
+            // escrow the course price for the mentor, released once the
+            // refund window is over
             log!(format!(
-                "Update Enrollment status to enrolled: {:?}",
-                enrollment.clone()
-            ));
-
-            // create new module progress for the student
-            let course = self.get_course_by_id(enrollment.course_id).unwrap();
-            for module_id in course.modules_ids.iter() {
-                let module = self.get_module_by_id(*module_id).unwrap();
-                let new_module_progress: ModuleProgress = ModuleProgress {
-                    id: self.module_progress_count,
-                    module_id: module.id,
-                    student_id: account_id.clone(),
-                    status: "not_started".to_string(),
-                    is_enrolled: true,
-                    progress: 0,
-                    completed_at: None,
-                };
-
-                // add module progress to the module progresses vector
-                self.module_progresses.push(new_module_progress);
-
-                // increment the module progress count
-                self.module_progress_count += 1;
-
-                // enroll the student in the module quizz if it exists
-                if module.quizz_id.is_some() {
-                    let quizz = self.get_quizz_by_id(module.quizz_id.unwrap()).unwrap();
-                    let new_quizz_progress: QuizzProgress = QuizzProgress {
-                        id: self.quizz_count,
-                        quizz_id: quizz.id,
-                        student_id: account_id.clone(),
-                        status: "not_started".to_string(),
-                        is_enrolled: true,
-                        try_count: 0,
-                        is_submitted: false,
-                        is_correct: false,
-                        completed_at: None,
-                    };
-
-                    // add quizz progress to the quizz progresses vector
-                    self.quizz_progresses.push(new_quizz_progress);
-
-                    // increment the quizz progress count
-                    self.quizz_progress_count += 1;
-                }
-
-                // enroll the student in the module lessons
-                for lesson_id in module.lessons_ids.iter() {
-                    let lesson = self.get_lesson_by_id(*lesson_id); 
-                    if lesson.is_none() {
-                        log_str(&format!(
-                            "Lesson {} does not exist",
-                            lesson_id
-                        ));
-                        continue;
-                    }
-
-                    // create new lesson progress for the student
-                    let new_lesson_progress: LessonProgress = LessonProgress {
-                        id: self.lesson_progress_count,
-                        lesson_id: lesson_id.clone(),
-                        student_id: account_id.clone(),
-                        status: "not_started".to_string(),
-                        is_enrolled: true,
-                        completed_at: None,
-                    };
-
-                    // add lesson progress to the lesson progresses vector
-                    self.lesson_progresses.push(new_lesson_progress);
-
-                    // increment the lesson progress count
-                    self.lesson_progress_count += 1;
-                }
-            }
-
-            // Log the enrollment of the student in the course
-            log_str(&format!(
-                "Student enrolled in course: {}",
-                course.title.clone()
+                "Escrowing {} NEAR for mentor: {}",
+                course.price, course.mentor_id
             ));
 
-            // trasnfer the course price to the course mentor
-            let course_mentor = course.mentor_id;
-            let transfer_amount = course.price;
+            // book the platform fee into the treasury
+            let course_fee = self.calculate_course_fee(course.price);
+            self.internal_record_payment(
+                carted_enrollment,
+                &course,
+                None,
+                course.price,
+                course_fee,
+            );
+
+            // emit the enrolled event
+            CourchainEvent::Enrolled(vec![EnrolledData {
+                enrollment_id: carted_enrollment.id,
+                course_id: course.id,
+                student_id: account_id.clone(),
+                mentor_id: course.mentor_id.clone(),
+                token_id: None,
+                price: U128(course.price),
+                fee: U128(course_fee),
+                enrolled_at,
+            }])
+            .emit();
+
+            receipt_courses.push(EnrolledCourseReceipt {
+                course_id: course.id,
+                enrollment_id: carted_enrollment.id,
+                price: U128(course.price),
+                fee: U128(course_fee),
+            });
+        }
 
-            log!(format!(
-                "Transfering {} NEAR to mentor: {}",
-                course.price, course_mentor
-            ));
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
 
-            Promise::new(course_mentor).transfer(transfer_amount);
+        // give back whatever was paid above the exact total
+        let refund = attached_deposit - total_courses_price;
+        if refund > 0 {
+            log_str(&format!("Refunding {} yoctoNEAR to {}", refund, account_id));
+            Promise::new(account_id.clone()).transfer(refund);
         }
+
+        Ok(EnrollmentReceipt {
+            student_id: account_id,
+            courses: receipt_courses,
+            total_price: U128(total_courses_price),
+            attached_deposit: U128(attached_deposit),
+            refund: U128(refund),
+        })
     }
 
-    pub fn submit_quizz(&mut self, quizz_id: u64, submitted_questions: Vec<Question>) {
+    #[handle_result]
+    pub fn submit_quizz(
+        &mut self,
+        quizz_id: u64,
+        selected_answers: Vec<u32>,
+    ) -> Result<QuizzResult, ContractError> {
         // only students can submit quizz
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the quizz exists
-        let quizz = self.get_quizz_by_id(quizz_id);
+        let quizz = self.internal_get_quizz(quizz_id);
         if quizz.is_none() {
-            log_str("Quizz does not exist");
-            env::panic_str("Quizz does not exist")
+            return Err(ContractError::NotFound("Quizz does not exist".to_string()));
         }
 
-        // get the quizz progress of the student
-        let quizz_progress = self
-            .get_quizz_progress(quizz_id, account_id.clone())
-            .unwrap();
+        // get the quizz progress of the student, created if the quizz was added after enrolling
+        let quizz_progress = self.internal_quizz_progress(quizz_id, &account_id);
 
         // check if quizz is enrolled
-        if !quizz_progress.is_enrolled {
-            log_str("Quizz is not enrolled");
-            env::panic_str("Quizz is not enrolled")
+        if quizz_progress.is_none() || !quizz_progress.as_ref().unwrap().is_enrolled {
+            return Err(ContractError::InvalidState(
+                "Quizz is not enrolled".to_string(),
+            ));
         }
 
         // check if quizz is already submitted correctly
+        let quizz_progress = quizz_progress.unwrap();
         if quizz_progress.is_correct {
-            log_str("Quizz is already complted correctly");
-            env::panic_str("Quizz is already complted correctly")
+            return Err(ContractError::InvalidState(
+                "Quizz is already complted correctly".to_string(),
+            ));
         }
 
-        // check if quizz questions === submitted questions
+        // every question needs exactly one selected answer
         let quizz = quizz.unwrap();
-        let correct_questions = quizz.questions.clone();
-        let mut correct_quizz = true;
-        for (i, question) in correct_questions.iter().enumerate() {
-            if question != &submitted_questions[i] {
-                correct_quizz = false;
-                break;
+        if selected_answers.len() != quizz.questions.len() {
+            return Err(ContractError::InvalidInput(
+                "Every question must have exactly one selected answer".to_string(),
+            ));
+        }
+
+        // grade each question against the stored correct answers
+        let mut question_results: Vec<QuestionResult> = vec![];
+        for (i, question) in quizz.questions.iter().enumerate() {
+            let selected_answer = selected_answers[i];
+            let answer = question.answers.get(selected_answer as usize);
+            if answer.is_none() {
+                return Err(ContractError::InvalidInput(format!(
+                    "Invalid answer index for question {}",
+                    i
+                )));
             }
+
+            question_results.push(QuestionResult {
+                question_index: i as u32,
+                selected_answer,
+                is_correct: answer.unwrap().is_correct,
+            });
         }
 
+        let correct_count = question_results.iter().filter(|r| r.is_correct).count() as u32;
+        let correct_quizz = correct_count as usize == quizz.questions.len();
+
         // update the quizz progress based on correct_quizz value
         let mut quizz_progress = quizz_progress.clone();
         quizz_progress.is_submitted = true;
         quizz_progress.try_count += 1;
-        quizz_progress.is_correct = correct_quizz.clone();
+        quizz_progress.is_correct = correct_quizz;
         if correct_quizz {
             // update the quizz progress status to completed
-            quizz_progress.status = "completed".to_string();
-
-            // get the index of the quizz progress in the quizz progresses vector
-            let index = self
-                .quizz_progresses
-                .iter()
-                .position(|qp| qp.id == quizz_progress.id)
-                .unwrap();
+            quizz_progress
+                .status
+                .transition_to(QuizzStatus::Completed)?;
 
             // update the quizz progress
-            self.quizz_progresses
-                .replace(index as u32, quizz_progress.clone());
+            self.quizz_progresses.insert(
+                (quizz_progress.quizz_id, account_id.clone()),
+                quizz_progress.clone(),
+            );
 
             // get module progress
-            let module_progress = self
-                .get_module_progress(quizz.module_id, account_id.clone())
-                .unwrap();
+            let module_progress = self.internal_module_progress(quizz.module_id, &account_id);
             // check if all lessons in the module are completed
             let module = self.get_module_by_id(quizz.module_id).unwrap();
-            let lessons = module.lessons_ids;
-            let mut all_lessons_completed = true;
-            for lesson_id in lessons.iter() {
-                let lesson_progress = self
-                    .get_lesson_progress(*lesson_id, account_id.clone())
-                    .unwrap();
-                if lesson_progress.status != "completed" {
-                    all_lessons_completed = false;
-                    break;
-                }
-            }
+            let all_lessons_completed =
+                self.is_student_module_completed(module.id, account_id.clone());
 
-            if all_lessons_completed {
-                let mut module_progress = module_progress.clone();
-                module_progress.status = "completed".to_string();
-                module_progress.completed_at = Some(env::block_timestamp());
+            // a module completed before new content was added stays completed
+            let module_progress = module_progress
+                .filter(|module_progress| module_progress.status != ProgressStatus::Completed);
 
-                // get the index of the module progress in the module progresses vector
-                let mod_index = self
-                    .module_progresses
-                    .iter()
-                    .position(|mp| mp.id == module_progress.id)
-                    .unwrap();
+            if let (true, Some(mut module_progress)) = (all_lessons_completed, module_progress) {
+                module_progress
+                    .status
+                    .transition_to(ProgressStatus::Completed)?;
+                module_progress.completed_at = Some(env::block_timestamp());
 
                 // update the module progress
-                self.module_progresses
-                    .replace(mod_index as u32, module_progress.clone());
+                self.module_progresses.insert(
+                    (module_progress.module_id, account_id.clone()),
+                    module_progress.clone(),
+                );
 
                 // shwo msg
                 log_str(&format!("Module completed: {}", module.title));
@@ -1063,166 +1706,176 @@ impl Contract {
             let course_id = module.course_id;
             let course = self.get_course_by_id(course_id).unwrap();
             let modules = course.clone().modules_ids;
-            let mut all_modules_completed = true;
-            for module_id in modules.iter() {
-                let module_progress = self
-                    .get_module_progress(*module_id, account_id.clone())
-                    .unwrap();
-                if module_progress.status != "completed" {
-                    all_modules_completed = false;
-                    break;
-                }
-            }
+            let all_modules_completed = modules
+                .iter()
+                .all(|module_id| self.is_student_module_completed(*module_id, account_id.clone()));
 
             // update the enrollment staus to completed if all modules are completed
-            if all_modules_completed {
+            if all_modules_completed
+                && self.is_student_course_enrolled(course_id, account_id.clone())
+            {
                 let course_id = module.course_id;
                 let enrollment = self.get_enrollment(course_id, account_id.clone()).unwrap();
                 let mut enrollment = enrollment.clone();
-                enrollment.status = "completed".to_string();
+                enrollment
+                    .status
+                    .transition_to(EnrollmentStatus::Completed)?;
                 enrollment.completed_at = Some(env::block_timestamp());
 
-                // get the index of the enrollment in the enrollments vector
-                let en_index = self
-                    .enrollments
-                    .iter()
-                    .position(|e| e.id == enrollment.id)
-                    .unwrap();
-
                 // update the enrollment
-                self.enrollments
-                    .replace(en_index as u32, enrollment.clone());
+                self.enrollments.insert(enrollment.id, enrollment.clone());
+
+                // issue the completion certificate
+                self.internal_mint_certificate(&enrollment, &course);
 
                 // Log the completion of the course
                 log_str(&format!("Course completed: {}", course.title));
+
+                // emit the course_completed event
+                CourchainEvent::CourseCompleted(vec![CourseCompletedData {
+                    enrollment_id: enrollment.id,
+                    course_id,
+                    student_id: account_id.clone(),
+                    certificate_id: self.enrollment_certificates.get(&enrollment.id).copied(),
+                    completed_at: enrollment.completed_at.unwrap(),
+                }])
+                .emit();
             }
         } else {
             // update the quizz progress status to submitted
-            quizz_progress.status = "submitted".to_string();
-
-            // get the index of the quizz progress in the quizz progresses vector
-            let index = self
-                .quizz_progresses
-                .iter()
-                .position(|qp| qp.id == quizz_progress.id)
-                .unwrap();
+            quizz_progress
+                .status
+                .transition_to(QuizzStatus::Submitted)?;
 
             // update the quizz progress
-            self.quizz_progresses
-                .replace(index as u32, quizz_progress.clone());
+            self.quizz_progresses.insert(
+                (quizz_progress.quizz_id, account_id.clone()),
+                quizz_progress.clone(),
+            );
+        }
+
+        // recompute the weighted module and course progress
+        if let Some(module) = self.get_module_by_id(quizz.module_id) {
+            self.internal_update_progress(module.course_id, &account_id);
         }
 
         // Log the submission of the quizz
         log_str(&format!("Quizz submitted: {}", quizz.title));
+
+        // emit the quiz_submitted event
+        CourchainEvent::QuizSubmitted(vec![QuizSubmittedData {
+            quizz_id,
+            module_id: quizz.module_id,
+            student_id: account_id.clone(),
+            correct_count,
+            total_questions: quizz.questions.len() as u32,
+            passed: correct_quizz,
+            try_count: quizz_progress.try_count,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(QuizzResult {
+            quizz_id,
+            questions: question_results,
+            correct_count,
+            total_questions: quizz.questions.len() as u32,
+            passed: correct_quizz,
+            try_count: quizz_progress.try_count,
+        })
     }
 
-    pub fn complete_lesson(&mut self, lesson_id: u64) -> bool {
+    #[handle_result]
+    pub fn complete_lesson(&mut self, lesson_id: u64) -> Result<bool, ContractError> {
         // only students can complete lessons
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the lesson exists
         let lesson = self.get_lesson_by_id(lesson_id);
         if lesson.is_none() {
-            log_str("Lesson does not exist");
-            return false;
+            return Err(ContractError::NotFound("Lesson does not exist".to_string()));
         }
 
         // check if the student is enrolled in the lesson course
         if !self.is_student_lesson_enrolled(lesson_id, account_id.clone()) {
-            log_str("Student is not enrolled in the lesson course");
-            return false;
+            return Err(ContractError::InvalidState(
+                "Student is not enrolled in the lesson course".to_string(),
+            ));
         }
 
         // check if the student has already completed the lesson
         if self.is_student_lesson_completed(lesson_id, account_id.clone()) {
-            log_str("Student has already completed the lesson");
-            return false;
+            return Err(ContractError::InvalidState(
+                "Student has already completed the lesson".to_string(),
+            ));
         }
 
-        log_str(&format!("Completing Lesson..."));
+        log_str("Completing Lesson...");
         let module_id = lesson.clone().unwrap().module_id;
 
-        // update the lesson progress
-        let lesson_progress = self
-            .get_lesson_progress(lesson_id, account_id.clone())
-            .unwrap();
-        let mut lesson_progress = lesson_progress.clone();
-        lesson_progress.status = "completed".to_string();
+        // update the lesson progress, created if the lesson was added after enrolling
+        let lesson_progress = self.internal_lesson_progress(lesson_id, &account_id);
+        if lesson_progress.is_none() {
+            return Err(ContractError::InvalidState(
+                "Student is not enrolled in the lesson course".to_string(),
+            ));
+        }
+        let mut lesson_progress = lesson_progress.unwrap();
+        lesson_progress
+            .status
+            .transition_to(ProgressStatus::Completed)?;
         lesson_progress.completed_at = Some(env::block_timestamp());
 
-        //  get index of the lesson progress in the lesson progresses vector
-        let less_index = self
-            .lesson_progresses
-            .iter()
-            .position(|lp| lp.id == lesson_progress.id)
-            .unwrap();
-
         // update the lesson progress
-        self.lesson_progresses
-            .replace(less_index as u32, lesson_progress.clone());
+        self.lesson_progresses.insert(
+            (lesson_progress.lesson_id, account_id.clone()),
+            lesson_progress.clone(),
+        );
 
         // update teh module progress status to started
-        let module_progress = self
-            .get_module_progress(module_id, account_id.clone())
-            .unwrap();
+        let module_progress = self.internal_module_progress(module_id, &account_id);
+        if module_progress.is_none() {
+            return Err(ContractError::InvalidState(
+                "Student is not enrolled in the lesson course".to_string(),
+            ));
+        }
+        let module_progress = module_progress.unwrap();
 
-        if module_progress.status == "not_started" {
+        if module_progress.status == ProgressStatus::NotStarted {
             let mut module_progress = module_progress.clone();
-            module_progress.status = "started".to_string();
-
-            // get the index of the module progress in the module progresses vector
-            let mod_index = self
-                .module_progresses
-                .iter()
-                .position(|mp| mp.id == module_progress.id)
-                .unwrap();
+            module_progress
+                .status
+                .transition_to(ProgressStatus::Started)?;
 
             // update the module progress
-            self.module_progresses
-                .replace(mod_index as u32, module_progress.clone());
+            self.module_progresses.insert(
+                (module_progress.module_id, account_id.clone()),
+                module_progress.clone(),
+            );
         }
 
         // check if all the lessons in the module are completed
         let module = self.get_module_by_id(module_id).unwrap();
-        // check if module quizz status = complted if quizz existed
-        let mut quizz_completed = true;
-        if module.quizz_id.is_some() {
-            let quizz_id = module.quizz_id.unwrap();
-            let quizz_progress = self
-                .get_quizz_progress(quizz_id, account_id.clone())
-                .unwrap();
-            if quizz_progress.status != "completed" {
-                quizz_completed = false;
-            }
-        }
-        let lessons = module.lessons_ids;
-        let mut all_lessons_completed = true;
-        for lesson_id in lessons.iter() {
-            let lesson_progress = self
-                .get_lesson_progress(*lesson_id, account_id.clone())
-                .unwrap();
-            if lesson_progress.status != "completed" {
-                all_lessons_completed = false;
-                break;
-            }
-        }
+        // lessons or a quizz added after enrolling count as not completed
+        let module_completed = self.is_student_module_completed(module_id, account_id.clone());
 
-        // update the module progress status to completed
-        if all_lessons_completed && quizz_completed {
+        // update the module progress status to completed, a module completed
+        // before new content was added stays completed
+        if module_completed && module_progress.status != ProgressStatus::Completed {
             let mut module_progress = module_progress.clone();
-            module_progress.status = "completed".to_string();
+            module_progress
+                .status
+                .transition_to(ProgressStatus::Completed)?;
             module_progress.completed_at = Some(env::block_timestamp());
 
-            // get the index of the module progress in the module progresses vector
-            let mod_index = self
-                .module_progresses
-                .iter()
-                .position(|mp| mp.id == module_progress.id)
-                .unwrap();
-
             // update the module progress
-            self.module_progresses
-                .replace(mod_index as u32, module_progress.clone());
+            self.module_progresses.insert(
+                (module_progress.module_id, account_id.clone()),
+                module_progress.clone(),
+            );
 
             // shwo msg
             log_str(&format!("Module completed: {}", module.title));
@@ -1232,75 +1885,99 @@ impl Contract {
         let course_id = module.course_id;
         let course = self.get_course_by_id(course_id).unwrap();
         let modules = course.clone().modules_ids;
-        let mut all_modules_completed = true;
-        for module_id in modules.iter() {
-            let module_progress = self
-                .get_module_progress(*module_id, account_id.clone())
-                .unwrap();
-            if module_progress.status != "completed" {
-                all_modules_completed = false;
-                break;
-            }
-        }
+        let all_modules_completed = modules
+            .iter()
+            .all(|module_id| self.is_student_module_completed(*module_id, account_id.clone()));
 
         // Log the completion of the lesson
         log_str(&format!("Lesson completed: {}", lesson.unwrap().title));
 
+        // emit the lesson_completed event
+        CourchainEvent::LessonCompleted(vec![LessonCompletedData {
+            lesson_id,
+            module_id,
+            course_id,
+            student_id: account_id.clone(),
+            completed_at: lesson_progress.completed_at.unwrap(),
+        }])
+        .emit();
+
+        // recompute the weighted module and course progress
+        self.internal_update_progress(course_id, &account_id);
+
+        // release the mentor's escrow if this lesson reached its milestone
+        if let Some(enrollment) = self.get_enrollment(course_id, account_id.clone()) {
+            self.internal_try_release_escrow(enrollment.id);
+        }
+
         // update the enrollment staus to completed if all modules are completed
-        if all_modules_completed {
+        if all_modules_completed && self.is_student_course_enrolled(course_id, account_id.clone()) {
             let course_id = module.course_id;
             let enrollment = self.get_enrollment(course_id, account_id.clone()).unwrap();
             let mut enrollment = enrollment.clone();
-            enrollment.status = "completed".to_string();
+            enrollment
+                .status
+                .transition_to(EnrollmentStatus::Completed)?;
             enrollment.completed_at = Some(env::block_timestamp());
 
-            // get the index of the enrollment in the enrollments vector
-            let en_index = self
-                .enrollments
-                .iter()
-                .position(|e| e.id == enrollment.id)
-                .unwrap();
-
             // update the enrollment
-            self.enrollments
-                .replace(en_index as u32, enrollment.clone());
+            self.enrollments.insert(enrollment.id, enrollment.clone());
+
+            // issue the completion certificate
+            self.internal_mint_certificate(&enrollment, &course);
 
             // Log the completion of the course
             log_str(&format!("Course completed: {}", course.title));
+
+            // emit the course_completed event
+            CourchainEvent::CourseCompleted(vec![CourseCompletedData {
+                enrollment_id: enrollment.id,
+                course_id,
+                student_id: account_id.clone(),
+                certificate_id: self.enrollment_certificates.get(&enrollment.id).copied(),
+                completed_at: enrollment.completed_at.unwrap(),
+            }])
+            .emit();
         }
 
-        true
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-    pub fn add_video_to_lesson(&mut self, lesson_id: u64, ipfs_url: String) -> bool {
+    #[handle_result]
+    pub fn add_video_to_lesson(
+        &mut self,
+        lesson_id: u64,
+        ipfs_url: String,
+    ) -> Result<bool, ContractError> {
         // Only the mentor of the lesson can add video to the lesson
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the lesson exists
         let lesson: Option<Lesson> = self.get_lesson_by_id(lesson_id);
         if lesson.is_none() {
-            log_str("Lesson does not exist");
-            return false;
+            return Err(ContractError::NotFound("Lesson does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the lesson
         let lesson: Lesson = lesson.unwrap();
         let module: Module = self.get_module_by_id(lesson.module_id).unwrap();
         let course: Course = self.get_course_by_id(module.course_id).unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the lesson can add video to the lesson");
-            return false;
-        }
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the lesson can add video to the lesson",
+        )?;
 
         // update the lesson video url
         let mut lesson: Lesson = lesson.clone();
         lesson.video_url = ipfs_url;
-
-        // get the index of the lesson in the lessons vector
-        let index: usize = self.lessons.iter().position(|l| l.id == lesson.id).unwrap();
+        lesson.updated_at = env::block_timestamp();
 
         // update the lesson
-        self.lessons.replace(index as u32, lesson.clone());
+        self.lessons.insert(lesson.id, lesson.clone());
 
         // Log the addition of the video to the lesson
         log_str(&format!(
@@ -1308,45 +1985,77 @@ impl Contract {
             lesson.video_url, lesson.title
         ));
 
-        true
+        // emit the lesson_updated event
+        CourchainEvent::LessonUpdated(vec![LessonUpdatedData {
+            lesson_id: lesson.id,
+            module_id: lesson.module_id,
+            title: lesson.title.clone(),
+            order: lesson.order,
+            updated_by: account_id.clone(),
+            updated_at: lesson.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
-    pub fn add_article_to_lesson(&mut self, lesson_id: u64, article: String) -> bool {
+    #[handle_result]
+    pub fn add_article_to_lesson(
+        &mut self,
+        lesson_id: u64,
+        article: String,
+    ) -> Result<bool, ContractError> {
         // Only the mentor of the lesson can add article to the lesson
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the lesson exists
         let lesson: Option<Lesson> = self.get_lesson_by_id(lesson_id);
         if lesson.is_none() {
-            log_str("Lesson does not exist");
-            return false;
+            return Err(ContractError::NotFound("Lesson does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the lesson
         let lesson: Lesson = lesson.unwrap();
         let module: Module = self.get_module_by_id(lesson.module_id).unwrap();
         let course: Course = self.get_course_by_id(module.course_id).unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the lesson can add article to the lesson");
-            return false;
-        }
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the lesson can add article to the lesson",
+        )?;
 
         // update the lesson article
         let mut lesson: Lesson = lesson.clone();
         lesson.article = article;
-
-        // get the index of the lesson in the lessons vector
-        let index: usize = self.lessons.iter().position(|l| l.id == lesson.id).unwrap();
+        lesson.updated_at = env::block_timestamp();
 
         // update the lesson
-        self.lessons.replace(index as u32, lesson.clone());
+        self.lessons.insert(lesson.id, lesson.clone());
 
         // Log the addition of the article to the lesson
         log_str(&format!("Article added to lesson: {}", lesson.title));
 
-        true
+        // emit the lesson_updated event
+        CourchainEvent::LessonUpdated(vec![LessonUpdatedData {
+            lesson_id: lesson.id,
+            module_id: lesson.module_id,
+            title: lesson.title.clone(),
+            order: lesson.order,
+            updated_by: account_id.clone(),
+            updated_at: lesson.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 
+    #[handle_result]
     pub fn update_course_details(
         &mut self,
         course_id: u64,
@@ -1354,24 +2063,23 @@ impl Contract {
         description: String,
         price: U128,
         category: String,
-        updated_at: u64,
-    ) -> bool {
+    ) -> Result<bool, ContractError> {
         // Only the mentor of the course can update the course details
         let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
 
         // check if the course exists
         let course: Option<Course> = self.get_course_by_id(course_id);
         if course.is_none() {
-            log_str("Course does not exist");
-            return false;
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
         }
 
         // check if the mentor is the mentor of the course
         let course: Course = course.unwrap();
-        if course.mentor_id != account_id {
-            log_str("Only the mentor of the course can update the course details");
-            return false;
-        }
+        self.require_course_mentor(
+            &course,
+            "Only the mentor of the course can update the course details",
+        )?;
 
         // update the course details
         let mut course: Course = course.clone();
@@ -1379,17 +2087,147 @@ impl Contract {
         course.description = description;
         course.price = u128::from(price);
         course.category = category;
-        course.updated_at = updated_at;
-
-        // get the index of the course in the courses vector
-        let index: usize = self.courses.iter().position(|c| c.id == course.id).unwrap();
+        course.updated_at = env::block_timestamp();
 
         // update the course
-        self.courses.replace(index as u32, course.clone());
+        self.courses.insert(course.id, course.clone());
 
         // Log the update of the course details
         log_str(&format!("Course details updated: {}", course.title));
 
-        true
+        // emit the course_updated event
+        CourchainEvent::CourseUpdated(vec![CourseUpdatedData {
+            course_id: course.id,
+            title: course.title.clone(),
+            price: U128(course.price),
+            category: course.category.clone(),
+            updated_by: account_id.clone(),
+            updated_at: course.updated_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
     }
 }
+
+impl Contract {
+    /// Moves a carted enrollment to enrolled and creates the student's
+    /// progress rows for every module, quizz and lesson of the course.
+    pub(crate) fn internal_enroll(
+        &mut self,
+        carted_enrollment: &Enrollment,
+        enrolled_at: u64,
+    ) -> Result<Course, ContractError> {
+        // update the enrollment status to enrolled
+        let mut enrollment: Enrollment = carted_enrollment.clone();
+        let account_id = enrollment.student_id.clone();
+
+        enrollment
+            .status
+            .transition_to(EnrollmentStatus::Enrolled)?;
+        enrollment.enrolled_at = Some(enrolled_at);
+        enrollment.updated_at = enrolled_at;
+
+        // update the enrollment
+        self.enrollments.insert(enrollment.id, enrollment.clone());
+
+        log!(format!(
+            "Update Enrollment status to enrolled: {:?}",
+            enrollment.clone()
+        ));
+
+        // create the module, quizz and lesson progresses of the student
+        let course = self.get_course_by_id(enrollment.course_id).unwrap();
+        for module_id in course.modules_ids.iter() {
+            if self
+                .internal_module_progress(*module_id, &account_id)
+                .is_none()
+            {
+                log_str(&format!("Module {} does not exist", module_id));
+                continue;
+            }
+            let module = self.get_module_by_id(*module_id).unwrap();
+
+            // enroll the student in the module quizz if it exists
+            if let Some(quizz_id) = module.quizz_id {
+                self.internal_quizz_progress(quizz_id, &account_id);
+            }
+
+            // enroll the student in the module lessons
+            for lesson_id in module.lessons_ids.iter() {
+                if self
+                    .internal_lesson_progress(*lesson_id, &account_id)
+                    .is_none()
+                {
+                    log_str(&format!("Lesson {} does not exist", lesson_id));
+                }
+            }
+        }
+
+        // Log the enrollment of the student in the course
+        log_str(&format!(
+            "Student enrolled in course: {}",
+            course.title.clone()
+        ));
+
+        Ok(course)
+    }
+
+    /// Accounts with an enrollment record in the course. Only their rows can
+    /// exist in the progress maps of the course content.
+    fn internal_course_student_ids(&self, course_id: u64) -> Vec<AccountId> {
+        self.internal_course_enrollments(course_id)
+            .into_iter()
+            .map(|enrollment| enrollment.student_id)
+            .collect()
+    }
+
+    /// Removes a lesson and the progress rows of `students` on it.
+    fn internal_remove_lesson(&mut self, lesson_id: u64, students: &[AccountId]) {
+        self.lessons.remove(&lesson_id);
+        for student_id in students.iter() {
+            self.lesson_progresses
+                .remove(&(lesson_id, student_id.clone()));
+        }
+    }
+
+    /// Removes a quizz and the progress rows of `students` on it.
+    fn internal_remove_quizz(&mut self, quizz_id: u64, students: &[AccountId]) {
+        self.quizzes.remove(&quizz_id);
+        for student_id in students.iter() {
+            self.quizz_progresses
+                .remove(&(quizz_id, student_id.clone()));
+        }
+    }
+
+    /// Removes a module with its lessons, its quizz and the progress rows of
+    /// `students` on all of them. The caller unlinks it from its course.
+    fn internal_remove_module(&mut self, module: &Module, students: &[AccountId]) {
+        for lesson_id in module.lessons_ids.iter() {
+            self.internal_remove_lesson(*lesson_id, students);
+        }
+
+        if let Some(quizz_id) = module.quizz_id {
+            self.internal_remove_quizz(quizz_id, students);
+        }
+
+        for student_id in students.iter() {
+            self.module_progresses
+                .remove(&(module.id, student_id.clone()));
+        }
+
+        self.modules.remove(&module.id);
+    }
+}
+
+/// Whether `ids` holds exactly the ids of `current`, in any order.
+fn is_permutation(current: &[u64], ids: &[u64]) -> bool {
+    let mut current = current.to_vec();
+    let mut ids = ids.to_vec();
+    current.sort_unstable();
+    ids.sort_unstable();
+    current == ids
+}
@@ -0,0 +1,725 @@
+use crate::errors::ContractError;
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::env::log_str;
+use near_sdk::serde::{Deserialize, Serialize};
+use near_sdk::store::Vector;
+use near_sdk::{env, near_bindgen, AccountId, FunctionError};
+
+/// Storage key the runtime keeps the root contract struct under.
+const STATE_KEY: &[u8] = b"STATE";
+
+/// `User` before its role was a `UserRole` and its timestamps came from the
+/// block.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct UserV0 {
+    pub account_id: AccountId,
+    pub name: String,
+    pub username: String,
+    pub email: String,
+    pub phone: String,
+    pub role: String,
+    pub by_google: bool,
+    pub certifications: Option<Vec<Certification>>,
+    pub picture: String,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<UserV0> for User {
+    fn from(user: UserV0) -> Self {
+        User {
+            account_id: user.account_id,
+            name: user.name,
+            username: user.username,
+            email: user.email,
+            phone: user.phone,
+            role: match user.role.as_str() {
+                "admin" => UserRole::Admin,
+                _ => UserRole::User,
+            },
+            by_google: user.by_google,
+            certifications: user.certifications,
+            picture: user.picture,
+            created_at: normalize_timestamp(user.created_at),
+            updated_at: normalize_timestamp(user.updated_at),
+            client_created_at: None,
+        }
+    }
+}
+
+/// `Course` before it carried review ratings, a typed status and block
+/// timestamps.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct CourseV0 {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub level: String,
+    pub duration: String,
+    pub status: String,
+    pub requirements: Vec<String>,
+    pub objectives: Vec<String>,
+    pub category: String,
+    pub picture: String,
+    pub with_ai: bool,
+    pub price: u128,
+    pub mentor_id: AccountId,
+    pub modules_ids: Vec<u64>,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<CourseV0> for Course {
+    fn from(course: CourseV0) -> Self {
+        Course {
+            id: course.id,
+            title: course.title,
+            description: course.description,
+            level: course.level,
+            duration: course.duration,
+            status: match course.status.as_str() {
+                "published" => CourseStatus::Published,
+                "archived" => CourseStatus::Archived,
+                _ => CourseStatus::Draft,
+            },
+            requirements: course.requirements,
+            objectives: course.objectives,
+            category: course.category,
+            picture: course.picture,
+            with_ai: course.with_ai,
+            price: course.price,
+            mentor_id: course.mentor_id,
+            modules_ids: course.modules_ids,
+            created_at: normalize_timestamp(course.created_at),
+            updated_at: normalize_timestamp(course.updated_at),
+            client_created_at: None,
+            rating_count: 0,
+            rating_sum: 0,
+            average_rating: 0.0,
+        }
+    }
+}
+
+/// `Module` before its status was typed; it held whatever the client sent,
+/// including its timestamps.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct ModuleV0 {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub status: String,
+    pub order: u64,
+    pub with_ai: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub course_id: u64,
+    pub lessons_ids: Vec<u64>,
+    pub quizz_id: Option<u64>,
+}
+
+impl From<ModuleV0> for Module {
+    fn from(module: ModuleV0) -> Self {
+        Module {
+            id: module.id,
+            title: module.title,
+            description: module.description,
+            status: match module.status.as_str() {
+                "published" => ModuleStatus::Published,
+                "archived" => ModuleStatus::Archived,
+                _ => ModuleStatus::Draft,
+            },
+            order: module.order,
+            with_ai: module.with_ai,
+            created_at: normalize_timestamp(module.created_at),
+            updated_at: normalize_timestamp(module.updated_at),
+            client_created_at: None,
+            course_id: module.course_id,
+            lessons_ids: module.lessons_ids,
+            quizz_id: module.quizz_id,
+        }
+    }
+}
+
+/// `Lesson` with client supplied timestamps.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct LessonV0 {
+    pub id: u64,
+    pub title: String,
+    pub description: String,
+    pub video_url: String,
+    pub article: String,
+    pub order: u64,
+    pub with_ai: bool,
+    pub module_id: u64,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<LessonV0> for Lesson {
+    fn from(lesson: LessonV0) -> Self {
+        Lesson {
+            id: lesson.id,
+            title: lesson.title,
+            description: lesson.description,
+            video_url: lesson.video_url,
+            article: lesson.article,
+            order: lesson.order,
+            with_ai: lesson.with_ai,
+            module_id: lesson.module_id,
+            created_at: normalize_timestamp(lesson.created_at),
+            updated_at: normalize_timestamp(lesson.updated_at),
+            client_created_at: None,
+        }
+    }
+}
+
+/// `Quizz` with client supplied timestamps.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct QuizzV0 {
+    pub id: u64,
+    pub module_id: u64,
+    pub title: String,
+    pub description: String,
+    pub questions: Vec<Question>,
+    pub with_ai: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+impl From<QuizzV0> for Quizz {
+    fn from(quizz: QuizzV0) -> Self {
+        Quizz {
+            id: quizz.id,
+            module_id: quizz.module_id,
+            title: quizz.title,
+            description: quizz.description,
+            questions: quizz.questions,
+            with_ai: quizz.with_ai,
+            created_at: normalize_timestamp(quizz.created_at),
+            updated_at: normalize_timestamp(quizz.updated_at),
+            client_created_at: None,
+        }
+    }
+}
+
+/// `Enrollment` before its status was typed.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct EnrollmentV0 {
+    pub id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub status: String,
+    pub progress: u16,
+    pub carted_at: u64,
+    pub enrolled_at: Option<u64>,
+    pub completed_at: Option<u64>,
+    pub course_review: Option<u64>,
+    pub updated_at: u64,
+}
+
+impl From<EnrollmentV0> for Enrollment {
+    fn from(enrollment: EnrollmentV0) -> Self {
+        Enrollment {
+            id: enrollment.id,
+            course_id: enrollment.course_id,
+            student_id: enrollment.student_id,
+            status: match enrollment.status.as_str() {
+                "enrolled" => EnrollmentStatus::Enrolled,
+                "completed" => EnrollmentStatus::Completed,
+                "refunded" => EnrollmentStatus::Refunded,
+                _ => EnrollmentStatus::Carted,
+            },
+            progress: enrollment.progress,
+            carted_at: normalize_timestamp(enrollment.carted_at),
+            enrolled_at: enrollment.enrolled_at.map(normalize_timestamp),
+            completed_at: enrollment.completed_at.map(normalize_timestamp),
+            course_review: enrollment.course_review,
+            updated_at: normalize_timestamp(enrollment.updated_at),
+        }
+    }
+}
+
+/// `ModuleProgress` before its status was typed.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct ModuleProgressV0 {
+    pub id: u64,
+    pub module_id: u64,
+    pub student_id: AccountId,
+    pub status: String,
+    pub is_enrolled: bool,
+    pub progress: u16,
+    pub completed_at: Option<u64>,
+}
+
+impl From<ModuleProgressV0> for ModuleProgress {
+    fn from(module_progress: ModuleProgressV0) -> Self {
+        ModuleProgress {
+            id: module_progress.id,
+            module_id: module_progress.module_id,
+            student_id: module_progress.student_id,
+            status: progress_status_from_v0(&module_progress.status),
+            is_enrolled: module_progress.is_enrolled,
+            progress: module_progress.progress,
+            completed_at: module_progress.completed_at.map(normalize_timestamp),
+        }
+    }
+}
+
+/// `LessonProgress` before its status was typed.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct LessonProgressV0 {
+    pub id: u64,
+    pub lesson_id: u64,
+    pub student_id: AccountId,
+    pub status: String,
+    pub is_enrolled: bool,
+    pub completed_at: Option<u64>,
+}
+
+impl From<LessonProgressV0> for LessonProgress {
+    fn from(lesson_progress: LessonProgressV0) -> Self {
+        LessonProgress {
+            id: lesson_progress.id,
+            lesson_id: lesson_progress.lesson_id,
+            student_id: lesson_progress.student_id,
+            status: progress_status_from_v0(&lesson_progress.status),
+            is_enrolled: lesson_progress.is_enrolled,
+            completed_at: lesson_progress.completed_at.map(normalize_timestamp),
+        }
+    }
+}
+
+/// `QuizzProgress` before its status was typed.
+#[derive(BorshDeserialize, BorshSerialize, Clone)]
+pub struct QuizzProgressV0 {
+    pub id: u64,
+    pub quizz_id: u64,
+    pub student_id: AccountId,
+    pub status: String,
+    pub try_count: u16,
+    pub is_enrolled: bool,
+    pub is_submitted: bool,
+    pub is_correct: bool,
+    pub completed_at: Option<u64>,
+}
+
+impl From<QuizzProgressV0> for QuizzProgress {
+    fn from(quizz_progress: QuizzProgressV0) -> Self {
+        QuizzProgress {
+            id: quizz_progress.id,
+            quizz_id: quizz_progress.quizz_id,
+            student_id: quizz_progress.student_id,
+            status: match quizz_progress.status.as_str() {
+                "completed" => QuizzStatus::Completed,
+                "submitted" => QuizzStatus::Submitted,
+                _ => QuizzStatus::NotStarted,
+            },
+            try_count: quizz_progress.try_count,
+            is_enrolled: quizz_progress.is_enrolled,
+            is_submitted: quizz_progress.is_submitted,
+            is_correct: quizz_progress.is_correct,
+            completed_at: quizz_progress.completed_at.map(normalize_timestamp),
+        }
+    }
+}
+
+/// Clients sent `Date.now()` milliseconds while the contract itself wrote
+/// nanosecond block timestamps. Anything below 10^15 cannot be a nanosecond
+/// time after 1970-01-12 and is scaled up, so the conversion is idempotent.
+fn normalize_timestamp(timestamp: u64) -> u64 {
+    if timestamp < 1_000_000_000_000_000 {
+        timestamp.saturating_mul(1_000_000)
+    } else {
+        timestamp
+    }
+}
+
+fn progress_status_from_v0(status: &str) -> ProgressStatus {
+    match status {
+        "completed" => ProgressStatus::Completed,
+        "started" => ProgressStatus::Started,
+        _ => ProgressStatus::NotStarted,
+    }
+}
+
+/// Layout of the root struct before state was versioned, where every
+/// collection was a `Vector` scanned linearly.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct ContractV0 {
+    pub owner_id: AccountId,
+    pub users: Vector<UserV0>,
+    pub courses: Vector<CourseV0>,
+    pub modules: Vector<ModuleV0>,
+    pub lessons: Vector<LessonV0>,
+    pub enrollments: Vector<EnrollmentV0>,
+    pub quizzes: Vector<QuizzV0>,
+    pub module_progresses: Vector<ModuleProgressV0>,
+    pub lesson_progresses: Vector<LessonProgressV0>,
+    pub quizz_progresses: Vector<QuizzProgressV0>,
+    pub user_count: u64,
+    pub course_count: u64,
+    pub module_count: u64,
+    pub lesson_count: u64,
+    pub assignment_count: u64,
+    pub enrollment_count: u64,
+    pub module_progress_count: u64,
+    pub lesson_progress_count: u64,
+    pub quizz_progress_count: u64,
+    pub quizz_count: u64,
+    pub test_count: u64,
+}
+
+/// Layout version of the root struct, stored under `STATE_VERSION_KEY`.
+/// States without the key predate versioning and use `ContractV0`.
+pub const CURRENT_STATE_VERSION: u8 = 1;
+
+/// Storage key of the layout version, kept apart from the root struct so it
+/// can be read without knowing the layout.
+const STATE_VERSION_KEY: &[u8] = b"STATE_VERSION";
+
+/// Every state layout that has been deployed, newest last.
+pub enum VersionedContract {
+    V0(Box<ContractV0>),
+    Current(Box<Contract>),
+}
+
+impl VersionedContract {
+    /// Reads the stored root struct with the layout its version names.
+    pub fn load() -> Self {
+        let data = env::storage_read(STATE_KEY);
+        if data.is_none() {
+            env::panic_str("Contract state does not exist")
+        }
+        let data = data.unwrap();
+
+        match read_state_version() {
+            0 => VersionedContract::V0(Box::new(
+                ContractV0::try_from_slice(&data)
+                    .unwrap_or_else(|_| env::panic_str("Cannot deserialize the v0 contract state")),
+            )),
+            CURRENT_STATE_VERSION => VersionedContract::Current(Box::new(
+                Contract::try_from_slice(&data)
+                    .unwrap_or_else(|_| env::panic_str("Cannot deserialize the contract state")),
+            )),
+            version => env::panic_str(&format!("Unknown contract state version {}", version)),
+        }
+    }
+
+    pub fn into_current(self) -> Contract {
+        match self {
+            VersionedContract::V0(old) => start_migration_from_v0(*old),
+            VersionedContract::Current(contract) => *contract,
+        }
+    }
+}
+
+fn read_state_version() -> u8 {
+    env::storage_read(STATE_VERSION_KEY)
+        .map(|data| {
+            u8::try_from_slice(&data)
+                .unwrap_or_else(|_| env::panic_str("Cannot deserialize the contract state version"))
+        })
+        .unwrap_or(0)
+}
+
+/// Stamps the state with the current layout version. A fresh deployment
+/// starts from `Default` without running `migrate`, so this runs with every
+/// flush and only writes when the key is missing.
+pub(crate) fn write_state_version() {
+    if !env::storage_has_key(STATE_VERSION_KEY) {
+        env::storage_write(
+            STATE_VERSION_KEY,
+            &CURRENT_STATE_VERSION.try_to_vec().unwrap(),
+        );
+    }
+}
+
+/// The v0 collection a migration is moving, in the order they are moved.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum MigrationStage {
+    Users,
+    Courses,
+    Modules,
+    Lessons,
+    Quizzes,
+    Enrollments,
+    ModuleProgresses,
+    LessonProgresses,
+    QuizzProgresses,
+    /// Deletes the moved v0 records.
+    Cleanup,
+}
+
+/// Where `migrate_state_batch` resumes: the record at `index` of `stage` is
+/// the next one to move.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MigrationCursor {
+    pub stage: MigrationStage,
+    pub index: u32,
+}
+
+/// The v0 collections of a state whose records are still being moved.
+#[derive(BorshDeserialize, BorshSerialize)]
+pub struct PendingMigrationV0 {
+    pub cursor: MigrationCursor,
+    pub users: Vector<UserV0>,
+    pub courses: Vector<CourseV0>,
+    pub modules: Vector<ModuleV0>,
+    pub lessons: Vector<LessonV0>,
+    pub enrollments: Vector<EnrollmentV0>,
+    pub quizzes: Vector<QuizzV0>,
+    pub module_progresses: Vector<ModuleProgressV0>,
+    pub lesson_progresses: Vector<LessonProgressV0>,
+    pub quizz_progresses: Vector<QuizzProgressV0>,
+}
+
+impl PendingMigrationV0 {
+    pub(crate) fn flush(&mut self) {
+        self.users.flush();
+        self.courses.flush();
+        self.modules.flush();
+        self.lessons.flush();
+        self.enrollments.flush();
+        self.quizzes.flush();
+        self.module_progresses.flush();
+        self.lesson_progresses.flush();
+        self.quizz_progresses.flush();
+    }
+
+    fn stage_len(&self, stage: MigrationStage) -> u32 {
+        match stage {
+            MigrationStage::Users => self.users.len(),
+            MigrationStage::Courses => self.courses.len(),
+            MigrationStage::Modules => self.modules.len(),
+            MigrationStage::Lessons => self.lessons.len(),
+            MigrationStage::Quizzes => self.quizzes.len(),
+            MigrationStage::Enrollments => self.enrollments.len(),
+            MigrationStage::ModuleProgresses => self.module_progresses.len(),
+            MigrationStage::LessonProgresses => self.lesson_progresses.len(),
+            MigrationStage::QuizzProgresses => self.quizz_progresses.len(),
+            MigrationStage::Cleanup => 0,
+        }
+    }
+
+    /// Pops one moved record, returns false once every v0 collection is empty.
+    fn pop_moved(&mut self) -> bool {
+        self.users.pop().is_some()
+            || self.courses.pop().is_some()
+            || self.modules.pop().is_some()
+            || self.lessons.pop().is_some()
+            || self.enrollments.pop().is_some()
+            || self.quizzes.pop().is_some()
+            || self.module_progresses.pop().is_some()
+            || self.lesson_progresses.pop().is_some()
+            || self.quizz_progresses.pop().is_some()
+    }
+}
+
+fn next_stage(stage: MigrationStage) -> MigrationStage {
+    match stage {
+        MigrationStage::Users => MigrationStage::Courses,
+        MigrationStage::Courses => MigrationStage::Modules,
+        MigrationStage::Modules => MigrationStage::Lessons,
+        MigrationStage::Lessons => MigrationStage::Quizzes,
+        MigrationStage::Quizzes => MigrationStage::Enrollments,
+        MigrationStage::Enrollments => MigrationStage::ModuleProgresses,
+        MigrationStage::ModuleProgresses => MigrationStage::LessonProgresses,
+        MigrationStage::LessonProgresses => MigrationStage::QuizzProgresses,
+        MigrationStage::QuizzProgresses | MigrationStage::Cleanup => MigrationStage::Cleanup,
+    }
+}
+
+#[near_bindgen]
+impl Contract {
+    /// Upgrades the stored root struct to the current layout. A v0 state
+    /// keeps its records in place; `migrate_state_batch` moves them.
+    #[private]
+    #[init(ignore_state)]
+    pub fn migrate() -> Self {
+        let versioned = VersionedContract::load();
+        if let VersionedContract::Current(contract) = versioned {
+            log_str("Contract state is already up to date");
+            return *contract;
+        }
+
+        let contract = versioned.into_current();
+        env::storage_write(
+            STATE_VERSION_KEY,
+            &CURRENT_STATE_VERSION.try_to_vec().unwrap(),
+        );
+
+        log_str("Contract state migrated, call migrate_state_batch until it returns true");
+
+        contract
+    }
+
+    /// Moves up to `limit` v0 records to the current collections, resuming
+    /// from the stored cursor. Writes from other methods are rejected until it
+    /// returns true.
+    pub fn migrate_state_batch(&mut self, limit: u32) -> bool {
+        self.assert_role(Role::Owner, "Only the owner can migrate the state");
+
+        let migration = self.migration.take();
+        if migration.is_none() {
+            log_str("There is no migration in progress");
+            return true;
+        }
+        let mut migration = migration.unwrap();
+
+        let mut moved: u32 = 0;
+        while moved < limit {
+            let cursor = migration.cursor;
+            if cursor.stage == MigrationStage::Cleanup {
+                if !migration.pop_moved() {
+                    log_str("Contract state migration finished");
+                    return true;
+                }
+            } else if cursor.index >= migration.stage_len(cursor.stage) {
+                migration.cursor = MigrationCursor {
+                    stage: next_stage(cursor.stage),
+                    index: 0,
+                };
+                continue;
+            } else {
+                self.internal_migrate_record(&migration, cursor);
+                migration.cursor.index += 1;
+            }
+            moved += 1;
+        }
+
+        log_str(&format!(
+            "Migrated {} records, resuming at {:?} {}",
+            moved, migration.cursor.stage, migration.cursor.index
+        ));
+        self.migration = Some(migration);
+
+        false
+    }
+
+    pub fn get_migration_cursor(&self) -> Option<MigrationCursor> {
+        self.migration.as_ref().map(|migration| migration.cursor)
+    }
+}
+
+impl Contract {
+    /// Rejects writes while v0 records are still being moved, so they cannot
+    /// clash with the records not moved yet.
+    pub(crate) fn assert_not_migrating(&self) {
+        if self.migration.is_some() {
+            ContractError::InvalidState("The contract state is being migrated".to_string()).panic()
+        }
+    }
+
+    /// Moves the v0 record under `cursor` to its current collection.
+    fn internal_migrate_record(&mut self, migration: &PendingMigrationV0, cursor: MigrationCursor) {
+        let index = cursor.index;
+        match cursor.stage {
+            // users move to the account keyed map; admins keep their rights
+            MigrationStage::Users => {
+                let user = migration.users.get(index).unwrap().clone();
+                if user.role == "admin" {
+                    self.internal_grant_role(user.account_id.clone(), Role::Admin);
+                }
+                self.internal_save_user(user.into());
+            }
+            // content moves to id keyed maps, so deletions cannot shift other
+            // records; courses gained rating fields and every record a typed
+            // status and nanosecond timestamps
+            MigrationStage::Courses => {
+                let course: Course = migration.courses.get(index).unwrap().clone().into();
+                // mentors keep managing their courses
+                self.internal_grant_role(course.mentor_id.clone(), Role::Mentor);
+                self.mentor_courses
+                    .entry(course.mentor_id.clone())
+                    .or_default()
+                    .push(course.id);
+                self.courses.insert(course.id, course);
+            }
+            MigrationStage::Modules => {
+                let module = migration.modules.get(index).unwrap().clone();
+                self.modules.insert(module.id, module.into());
+            }
+            MigrationStage::Lessons => {
+                let lesson = migration.lessons.get(index).unwrap().clone();
+                self.lessons.insert(lesson.id, lesson.into());
+            }
+            MigrationStage::Quizzes => {
+                let quizz = migration.quizzes.get(index).unwrap().clone();
+                self.quizzes.insert(quizz.id, quizz.into());
+            }
+            MigrationStage::Enrollments => {
+                let enrollment = migration.enrollments.get(index).unwrap().clone();
+                self.internal_add_enrollment(enrollment.into());
+            }
+            MigrationStage::ModuleProgresses => {
+                let module_progress = migration.module_progresses.get(index).unwrap().clone();
+                self.module_progresses.insert(
+                    (
+                        module_progress.module_id,
+                        module_progress.student_id.clone(),
+                    ),
+                    module_progress.into(),
+                );
+            }
+            MigrationStage::LessonProgresses => {
+                let lesson_progress = migration.lesson_progresses.get(index).unwrap().clone();
+                self.lesson_progresses.insert(
+                    (
+                        lesson_progress.lesson_id,
+                        lesson_progress.student_id.clone(),
+                    ),
+                    lesson_progress.into(),
+                );
+            }
+            MigrationStage::QuizzProgresses => {
+                let quizz_progress = migration.quizz_progresses.get(index).unwrap().clone();
+                self.quizz_progresses.insert(
+                    (quizz_progress.quizz_id, quizz_progress.student_id.clone()),
+                    quizz_progress.into(),
+                );
+            }
+            MigrationStage::Cleanup => {}
+        }
+    }
+}
+
+/// Swaps the v0 root for the current one. The v0 collections stay where they
+/// are and are moved in batches by `migrate_state_batch`.
+fn start_migration_from_v0(old: ContractV0) -> Contract {
+    Contract {
+        owner_id: old.owner_id.clone(),
+        user_count: old.user_count,
+        course_count: old.course_count,
+        module_count: old.module_count,
+        lesson_count: old.lesson_count,
+        assignment_count: old.assignment_count,
+        enrollment_count: old.enrollment_count,
+        module_progress_count: old.module_progress_count,
+        lesson_progress_count: old.lesson_progress_count,
+        quizz_progress_count: old.quizz_progress_count,
+        quizz_count: old.quizz_count,
+        test_count: old.test_count,
+        migration: Some(PendingMigrationV0 {
+            cursor: MigrationCursor {
+                stage: MigrationStage::Users,
+                index: 0,
+            },
+            users: old.users,
+            courses: old.courses,
+            modules: old.modules,
+            lessons: old.lessons,
+            enrollments: old.enrollments,
+            quizzes: old.quizzes,
+            module_progresses: old.module_progresses,
+            lesson_progresses: old.lesson_progresses,
+            quizz_progresses: old.quizz_progresses,
+        }),
+        ..Contract::default()
+    }
+}
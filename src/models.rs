@@ -1,6 +1,9 @@
+use crate::errors::ContractError;
 use near_sdk::borsh::{self, BorshDeserialize, BorshSerialize};
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8, U128};
 use near_sdk::serde::{Deserialize, Serialize};
 use near_sdk::AccountId;
+use std::fmt::Debug;
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
 #[serde(crate = "near_sdk::serde")]
@@ -10,13 +13,232 @@ pub struct User {
     pub username: String,
     pub email: String,
     pub phone: String,
-    pub role: String,
+    pub role: UserRole,
     pub by_google: bool,
     pub certifications: Option<Vec<Certification>>,
     pub picture: String,
     pub created_at: u64,
     pub updated_at: u64,
+    /// Creation time reported by the client, kept for display only.
+    pub client_created_at: Option<u64>,
 }
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum Role {
+    Owner,
+    Admin,
+    Moderator,
+    Mentor,
+}
+
+impl Role {
+    /// The role an account needs in order to grant or revoke this role.
+    pub fn managed_by(&self) -> Role {
+        match self {
+            Role::Owner | Role::Admin => Role::Owner,
+            Role::Moderator | Role::Mentor => Role::Admin,
+        }
+    }
+
+    /// Whether holding this role also satisfies a check for `other`.
+    pub fn implies(&self, other: Role) -> bool {
+        match self {
+            Role::Owner => true,
+            Role::Admin => other != Role::Owner,
+            Role::Moderator | Role::Mentor => *self == other,
+        }
+    }
+}
+
+/// Profile role shown to clients; permissions live in the `Role` registry.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum UserRole {
+    User,
+    Admin,
+}
+
+/// State machine shared by the status enums.
+pub trait StatusTransition: Copy + Debug {
+    fn can_transition_to(&self, next: Self) -> bool;
+
+    /// Moves to `next`, rejecting transitions the state machine does not allow.
+    fn transition_to(&mut self, next: Self) -> Result<(), ContractError> {
+        if !self.can_transition_to(next) {
+            return Err(ContractError::InvalidState(format!(
+                "Cannot change status from {:?} to {:?}",
+                self, next
+            )));
+        }
+        *self = next;
+        Ok(())
+    }
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum CourseStatus {
+    Draft,
+    Published,
+    Archived,
+}
+
+impl StatusTransition for CourseStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (CourseStatus::Draft, CourseStatus::Published)
+                | (CourseStatus::Draft, CourseStatus::Archived)
+                | (CourseStatus::Published, CourseStatus::Archived)
+        )
+    }
+}
+
+/// Set by the mentor when creating a module; "created" is accepted as
+/// `Draft` for older clients.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ModuleStatus {
+    #[serde(alias = "created")]
+    Draft,
+    Published,
+    Archived,
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum EnrollmentStatus {
+    Carted,
+    Enrolled,
+    Completed,
+    Refunded,
+    // the refund transfer is in flight; it goes back to `Enrolled` if it fails
+    Refunding,
+}
+
+impl StatusTransition for EnrollmentStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (EnrollmentStatus::Carted, EnrollmentStatus::Enrolled)
+                | (EnrollmentStatus::Enrolled, EnrollmentStatus::Completed)
+                | (EnrollmentStatus::Enrolled, EnrollmentStatus::Refunding)
+                | (EnrollmentStatus::Refunding, EnrollmentStatus::Refunded)
+                | (EnrollmentStatus::Refunding, EnrollmentStatus::Enrolled)
+        )
+    }
+}
+
+/// Status of a module or lesson progress.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum ProgressStatus {
+    NotStarted,
+    Started,
+    Completed,
+}
+
+impl StatusTransition for ProgressStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (ProgressStatus::NotStarted, ProgressStatus::Started)
+                | (ProgressStatus::NotStarted, ProgressStatus::Completed)
+                | (ProgressStatus::Started, ProgressStatus::Completed)
+        )
+    }
+}
+
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum QuizzStatus {
+    NotStarted,
+    Submitted,
+    Completed,
+}
+
+impl StatusTransition for QuizzStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (QuizzStatus::NotStarted, QuizzStatus::Submitted)
+                | (QuizzStatus::NotStarted, QuizzStatus::Completed)
+                | (QuizzStatus::Submitted, QuizzStatus::Submitted)
+                | (QuizzStatus::Submitted, QuizzStatus::Completed)
+        )
+    }
+}
+
+/// Status of a mentor payout; it settles once the transfer callback runs.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PayoutStatus {
+    Pending,
+    Completed,
+    Failed,
+}
+
+impl StatusTransition for PayoutStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (PayoutStatus::Pending, PayoutStatus::Completed)
+                | (PayoutStatus::Pending, PayoutStatus::Failed)
+        )
+    }
+}
+
+/// Status of an enrollment payment. Only `Escrowed` payments can be
+/// refunded; their fee is kept out of the treasury until they are `Settled`,
+/// which happens once the refund window closes.
+#[derive(
+    BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Copy, PartialEq,
+)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum PaymentStatus {
+    Escrowed,
+    Released,
+    Refunded,
+    Refunding,
+    Settled,
+}
+
+impl StatusTransition for PaymentStatus {
+    fn can_transition_to(&self, next: Self) -> bool {
+        matches!(
+            (self, next),
+            (PaymentStatus::Escrowed, PaymentStatus::Settled)
+                | (PaymentStatus::Escrowed, PaymentStatus::Refunding)
+                | (PaymentStatus::Settled, PaymentStatus::Released)
+                | (PaymentStatus::Refunding, PaymentStatus::Refunded)
+                | (PaymentStatus::Refunding, PaymentStatus::Escrowed)
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum CertificateStatus {
+    Valid,
+    Revoked,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct Certification {
@@ -37,7 +259,7 @@ pub struct Course {
     pub description: String,
     pub level: String,
     pub duration: String,
-    pub status: String,
+    pub status: CourseStatus,
     pub requirements: Vec<String>,
     pub objectives: Vec<String>,
     pub category: String,
@@ -49,6 +271,12 @@ pub struct Course {
     pub modules_ids: Vec<u64>,
     pub created_at: u64,
     pub updated_at: u64,
+    pub client_created_at: Option<u64>,
+    pub rating_count: u64,
+    pub rating_sum: u64,
+    /// `rating_sum / rating_count`, 0 until the first review. Kept up to
+    /// date with every review so course lists can show it.
+    pub average_rating: f32,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
@@ -57,11 +285,12 @@ pub struct Module {
     pub id: u64,
     pub title: String,
     pub description: String,
-    pub status: String,
+    pub status: ModuleStatus,
     pub order: u64,
     pub with_ai: bool,
     pub created_at: u64,
     pub updated_at: u64,
+    pub client_created_at: Option<u64>,
     pub course_id: u64,
     pub lessons_ids: Vec<u64>,
     pub quizz_id: Option<u64>,
@@ -80,6 +309,7 @@ pub struct Lesson {
     pub module_id: u64,
     pub created_at: u64,
     pub updated_at: u64,
+    pub client_created_at: Option<u64>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
@@ -93,6 +323,7 @@ pub struct Quizz {
     pub with_ai: bool,
     pub created_at: u64,
     pub updated_at: u64,
+    pub client_created_at: Option<u64>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -109,6 +340,82 @@ pub struct Answer {
     pub is_correct: bool,
 }
 
+/// Student-facing quizz: same content as `Quizz` without the correctness flags.
+/// This only keeps the answers out of the getters: contract state is public,
+/// so anyone can still read them through a raw `view_state` RPC query.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizzView {
+    pub id: u64,
+    pub module_id: u64,
+    pub title: String,
+    pub description: String,
+    pub questions: Vec<QuestionView>,
+    pub with_ai: bool,
+    pub created_at: u64,
+    pub updated_at: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuestionView {
+    pub text: String,
+    pub answers: Vec<AnswerView>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct AnswerView {
+    pub text: String,
+}
+
+impl From<&Quizz> for QuizzView {
+    fn from(quizz: &Quizz) -> Self {
+        QuizzView {
+            id: quizz.id,
+            module_id: quizz.module_id,
+            title: quizz.title.clone(),
+            description: quizz.description.clone(),
+            questions: quizz
+                .questions
+                .iter()
+                .map(|question| QuestionView {
+                    text: question.text.clone(),
+                    answers: question
+                        .answers
+                        .iter()
+                        .map(|answer| AnswerView {
+                            text: answer.text.clone(),
+                        })
+                        .collect(),
+                })
+                .collect(),
+            with_ai: quizz.with_ai,
+            created_at: quizz.created_at,
+            updated_at: quizz.updated_at,
+        }
+    }
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuestionResult {
+    pub question_index: u32,
+    pub selected_answer: u32,
+    pub is_correct: bool,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct QuizzResult {
+    pub quizz_id: u64,
+    pub questions: Vec<QuestionResult>,
+    pub correct_count: u32,
+    pub total_questions: u32,
+    pub passed: bool,
+    pub try_count: u16,
+}
+
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FullLesson {
@@ -129,13 +436,13 @@ pub struct FullModule {
     pub id: u64,
     pub title: String,
     pub description: String,
-    pub status: String,
+    pub status: ModuleStatus,
     pub order: u64,
     pub with_ai: bool,
     pub created_at: u64,
     pub updated_at: u64,
     pub lessons: Vec<FullLesson>,
-    pub quizz: Option<Quizz>,
+    pub quizz: Option<QuizzView>,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
@@ -146,7 +453,7 @@ pub struct FullCourse {
     pub description: String,
     pub level: String,
     pub duration: String,
-    pub status: String,
+    pub status: CourseStatus,
     pub requirements: Vec<String>,
     pub objectives: Vec<String>,
     pub category: String,
@@ -157,6 +464,8 @@ pub struct FullCourse {
     pub modules: Vec<FullModule>,
     pub created_at: u64,
     pub updated_at: u64,
+    pub rating_count: u64,
+    pub average_rating: f32,
 }
 
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
@@ -165,7 +474,7 @@ pub struct Enrollment {
     pub id: u64,
     pub course_id: u64,
     pub student_id: AccountId,
-    pub status: String,
+    pub status: EnrollmentStatus,
     pub progress: u16,
     pub carted_at: u64,
     pub enrolled_at: Option<u64>,
@@ -180,7 +489,7 @@ pub struct ModuleProgress {
     pub id: u64,
     pub module_id: u64,
     pub student_id: AccountId,
-    pub status: String,
+    pub status: ProgressStatus,
     pub is_enrolled: bool,
     pub progress: u16,
     pub completed_at: Option<u64>,
@@ -192,7 +501,7 @@ pub struct LessonProgress {
     pub id: u64,
     pub lesson_id: u64,
     pub student_id: AccountId,
-    pub status: String,
+    pub status: ProgressStatus,
     pub is_enrolled: bool,
     pub completed_at: Option<u64>,
 }
@@ -203,17 +512,14 @@ pub struct QuizzProgress {
     pub id: u64,
     pub quizz_id: u64,
     pub student_id: AccountId,
-    pub status: String,
+    pub status: QuizzStatus,
     pub try_count: u16,
     pub is_enrolled: bool,
     pub is_submitted: bool,
     pub is_correct: bool,
     pub completed_at: Option<u64>,
-
 }
 
-
-
 #[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
 #[serde(crate = "near_sdk::serde")]
 pub struct FullEnrollment {
@@ -221,7 +527,7 @@ pub struct FullEnrollment {
     pub course: Course,
     pub student: User,
     pub modules: Vec<FullModuleProgress>,
-    pub status: String,
+    pub status: EnrollmentStatus,
     pub progress: u16,
     pub carted_at: u64,
     pub enrolled_at: Option<u64>,
@@ -237,7 +543,7 @@ pub struct FullModuleProgress {
     pub module: FullModule,
     pub student: User,
     pub lessons: Vec<FullLessonProgress>,
-    pub status: String,
+    pub status: ProgressStatus,
     pub is_enrolled: bool,
     pub progress: u16,
     pub quizz: Option<FullQuizzProgress>,
@@ -248,9 +554,9 @@ pub struct FullModuleProgress {
 #[serde(crate = "near_sdk::serde")]
 pub struct FullQuizzProgress {
     pub id: u64,
-    pub quizz: Quizz,
+    pub quizz: QuizzView,
     pub student: User,
-    pub status: String,
+    pub status: QuizzStatus,
     pub try_count: u16,
     pub is_enrolled: bool,
     pub is_submitted: bool,
@@ -264,7 +570,7 @@ pub struct FullLessonProgress {
     pub id: u64,
     pub lesson: FullLesson,
     pub student: User,
-    pub status: String,
+    pub status: ProgressStatus,
     pub is_enrolled: bool,
     pub completed_at: Option<u64>,
 }
@@ -277,7 +583,7 @@ pub struct CourseWithProgress {
     pub description: String,
     pub level: String,
     pub duration: String,
-    pub status: String,
+    pub status: CourseStatus,
     pub requirements: Vec<String>,
     pub objectives: Vec<String>,
     pub category: String,
@@ -291,3 +597,353 @@ pub struct CourseWithProgress {
     pub updated_at: u64,
     pub progress: u64,
 }
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageAccount {
+    pub total: u128,
+    pub used_bytes: u64,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalance {
+    pub total: U128,
+    pub available: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StorageBalanceBounds {
+    pub min: U128,
+    pub max: Option<U128>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct PendingUpgrade {
+    pub code_hash: Base58CryptoHash,
+    pub code_size: u64,
+    pub staged_by: AccountId,
+    pub staged_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EnrolledCourseReceipt {
+    pub course_id: u64,
+    pub enrollment_id: u64,
+    pub price: U128,
+    pub fee: U128,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EnrollmentReceipt {
+    pub student_id: AccountId,
+    pub courses: Vec<EnrolledCourseReceipt>,
+    pub total_price: U128,
+    pub attached_deposit: U128,
+    pub refund: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MentorEarnings {
+    pub lifetime: u128,
+    pub escrowed: u128,
+    pub pending: u128,
+    pub withdrawn: u128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct MentorEarningsView {
+    pub mentor_id: AccountId,
+    pub token_id: Option<AccountId>, // None for NEAR
+    pub lifetime: U128,
+    pub escrowed: U128,
+    pub pending: U128,
+    pub withdrawn: U128,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Payout {
+    pub id: u64,
+    pub mentor_id: AccountId,
+    pub token_id: Option<AccountId>, // None for NEAR
+    pub amount: U128,
+    pub status: PayoutStatus,
+    pub requested_at: u64,
+}
+
+/// `msg` of an `ft_transfer_call` paying for carted courses.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct FtEnrollMsg {
+    pub course_ids: Vec<u64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseTokenPrice {
+    pub token_id: AccountId,
+    pub price: U128,
+}
+
+/// Soulbound completion certificate, exposed as a NEP-171 token whose id is
+/// `id` as a string.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Certificate {
+    pub id: u64,
+    pub course_id: u64,
+    pub enrollment_id: u64,
+    pub mentor_id: AccountId,
+    pub student_id: AccountId,
+    pub title: String,
+    pub picture: String,
+    pub completed_at: u64,
+    pub quizzes_total: u32,
+    pub quizzes_passed: u32,
+    pub quizz_attempts: u32,
+    pub revoked_at: Option<u64>,
+    pub revoked_by: Option<AccountId>,
+    pub revocation_reason: Option<String>,
+}
+
+/// Public answer to "did this student finish this course".
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CertificateVerification {
+    pub certificate_id: u64,
+    pub token_id: String,
+    pub status: CertificateStatus,
+    pub course_id: u64,
+    pub course_title: String,
+    pub mentor_id: AccountId,
+    pub student_id: AccountId,
+    pub completed_at: u64,
+    pub quizzes_total: u32,
+    pub quizzes_passed: u32,
+    pub quizz_attempts: u32,
+    pub revoked_at: Option<u64>,
+    pub revocation_reason: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftToken {
+    pub token_id: String,
+    pub owner_id: AccountId,
+    pub metadata: TokenMetadata,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct TokenMetadata {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub media: Option<String>,
+    pub media_hash: Option<Base64VecU8>,
+    pub copies: Option<u64>,
+    pub issued_at: Option<u64>,
+    pub expires_at: Option<u64>,
+    pub starts_at: Option<u64>,
+    pub updated_at: Option<u64>,
+    pub extra: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<Base64VecU8>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct NftContractMetadata {
+    pub spec: String,
+    pub name: String,
+    pub symbol: String,
+    pub icon: Option<String>,
+    pub base_uri: Option<String>,
+    pub reference: Option<String>,
+    pub reference_hash: Option<Base64VecU8>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct Review {
+    pub id: u64,
+    pub course_id: u64,
+    pub enrollment_id: u64,
+    pub student_id: AccountId,
+    pub rating: u8,
+    pub text: String,
+    pub mentor_reply: Option<String>,
+    pub replied_at: Option<u64>,
+    pub is_hidden: bool,
+    pub hidden_by: Option<AccountId>,
+    pub created_at: u64,
+    pub updated_at: u64,
+    pub client_created_at: Option<u64>,
+}
+
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct RefundPolicy {
+    pub window_seconds: u64,
+    pub max_progress: u16, // percent of lessons completed
+}
+
+/// What was paid for one enrollment and where the mentor's share is.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct EnrollmentPayment {
+    pub enrollment_id: u64,
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub mentor_id: AccountId,
+    pub token_id: Option<AccountId>, // None for NEAR
+    pub price: U128,
+    pub fee: U128,
+    pub escrowed: U128,
+    pub released: U128,
+    pub escrow_policy: Option<CourseEscrowPolicy>,
+    pub status: PaymentStatus,
+    pub paid_at: u64,
+    pub settled_at: Option<u64>,
+}
+
+/// Share of a sale released to the mentor at enrollment, it is not refunded;
+/// the rest is held until the student completes `milestone_percent` of the
+/// lessons or `timeout_seconds` pass.
+#[derive(BorshDeserialize, BorshSerialize, Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseEscrowPolicy {
+    pub upfront_percent: u16,
+    pub milestone_percent: u16,
+    pub timeout_seconds: u64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct ModuleProgressReport {
+    pub module_id: u64,
+    pub title: String,
+    pub status: ProgressStatus,
+    pub progress: u16,
+    pub completed_lessons: u32,
+    pub total_lessons: u32,
+    pub quizz_id: Option<u64>,
+    pub quizz_status: Option<QuizzStatus>,
+    pub quizz_passed: bool,
+}
+
+/// Progress of a student in a course. Lessons and quizzes weigh the same.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct StudentProgressReport {
+    pub course_id: u64,
+    pub student_id: AccountId,
+    pub enrollment_status: EnrollmentStatus,
+    pub progress: u16,
+    pub completed_lessons: u32,
+    pub total_lessons: u32,
+    pub completed_quizzes: u32,
+    pub total_quizzes: u32,
+    pub modules: Vec<ModuleProgressReport>,
+}
+
+/// Filter of `search_courses`, unset fields match every course.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseFilter {
+    pub category: Option<String>,
+    pub level: Option<String>,
+    pub status: Option<CourseStatus>,
+    pub min_price: Option<U128>,
+    pub max_price: Option<U128>,
+    pub with_ai: Option<bool>,
+    pub mentor_id: Option<AccountId>,
+}
+
+impl CourseFilter {
+    pub fn matches(&self, course: &Course) -> bool {
+        if let Some(category) = &self.category {
+            if &course.category != category {
+                return false;
+            }
+        }
+        if let Some(level) = &self.level {
+            if &course.level != level {
+                return false;
+            }
+        }
+        if let Some(status) = self.status {
+            if course.status != status {
+                return false;
+            }
+        }
+        if let Some(min_price) = self.min_price {
+            if course.price < min_price.0 {
+                return false;
+            }
+        }
+        if let Some(max_price) = self.max_price {
+            if course.price > max_price.0 {
+                return false;
+            }
+        }
+        if let Some(with_ai) = self.with_ai {
+            if course.with_ai != with_ai {
+                return false;
+            }
+        }
+        if let Some(mentor_id) = &self.mentor_id {
+            if &course.mentor_id != mentor_id {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Order of the matches within one `search_courses` page. Pages themselves
+/// always follow course ids.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(crate = "near_sdk::serde", rename_all = "snake_case")]
+pub enum CourseSort {
+    Newest,
+    PriceAsc,
+    PriceDesc,
+    Rating,
+}
+
+/// One page of `search_courses`; `next_index` is the course id to resume
+/// from, `None` once the catalog has been scanned to the end.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(crate = "near_sdk::serde")]
+pub struct CourseSearchPage {
+    pub courses: Vec<Course>,
+    pub next_index: Option<u64>,
+}
+
+/// Result of `audit_integrity` for one range of ids. Every entry is an id
+/// that is referenced but has no record, paired with the record holding the
+/// reference; `is_consistent` only covers the audited range.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[serde(crate = "near_sdk::serde")]
+pub struct IntegrityReport {
+    /// `(course_id, module_id)` of `Course.modules_ids` entries.
+    pub dangling_course_modules: Vec<(u64, u64)>,
+    /// `(module_id, lesson_id)` of `Module.lessons_ids` entries.
+    pub dangling_module_lessons: Vec<(u64, u64)>,
+    /// `(module_id, quizz_id)` of `Module.quizz_id` values.
+    pub dangling_module_quizzes: Vec<(u64, u64)>,
+    /// `(module_id, student_id)` keys of module progress rows.
+    pub dangling_module_progresses: Vec<(u64, AccountId)>,
+    /// `(lesson_id, student_id)` keys of lesson progress rows.
+    pub dangling_lesson_progresses: Vec<(u64, AccountId)>,
+    /// `(quizz_id, student_id)` keys of quizz progress rows.
+    pub dangling_quizz_progresses: Vec<(u64, AccountId)>,
+    pub is_consistent: bool,
+}
@@ -0,0 +1,244 @@
+use crate::getters::{page_bounds, MAX_PAGE_LIMIT};
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, near_bindgen, AccountId, PromiseOrValue};
+
+pub const NFT_METADATA_SPEC: &str = "nft-1.0.0";
+
+#[near_bindgen]
+impl Contract {
+    /// Certificates are soulbound, transfers always fail.
+    #[payable]
+    pub fn nft_transfer(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+    ) {
+        let _ = (receiver_id, token_id, approval_id, memo);
+        env::panic_str("Certificates are non-transferable")
+    }
+
+    #[payable]
+    pub fn nft_transfer_call(
+        &mut self,
+        receiver_id: AccountId,
+        token_id: String,
+        approval_id: Option<u64>,
+        memo: Option<String>,
+        msg: String,
+    ) -> PromiseOrValue<bool> {
+        let _ = (receiver_id, token_id, approval_id, memo, msg);
+        env::panic_str("Certificates are non-transferable")
+    }
+
+    /// Revoked certificates are burned tokens, `verify_certificate` still
+    /// reports them as revoked.
+    pub fn nft_token(&self, token_id: String) -> Option<NftToken> {
+        let certificate_id = token_id.parse::<u64>().ok()?;
+        self.certificates
+            .get(&certificate_id)
+            .filter(|certificate| certificate.revoked_at.is_none())
+            .map(|certificate| self.internal_certificate_token(certificate))
+    }
+
+    pub fn nft_metadata(&self) -> NftContractMetadata {
+        NftContractMetadata {
+            spec: NFT_METADATA_SPEC.to_string(),
+            name: "Course Completion Certificates".to_string(),
+            symbol: "CERT".to_string(),
+            icon: None,
+            base_uri: None,
+            reference: None,
+            reference_hash: None,
+        }
+    }
+
+    pub fn nft_total_supply(&self) -> U128 {
+        U128((self.certificates.len() as u64 - self.revoked_certificate_count) as u128)
+    }
+
+    pub fn nft_tokens(&self, from_index: Option<U128>, limit: Option<u64>) -> Vec<NftToken> {
+        let (from_index, limit) = page_bounds(
+            from_index.map(|index| index.0 as u64),
+            limit,
+            MAX_PAGE_LIMIT,
+        );
+
+        self.certificates
+            .values()
+            .filter(|certificate| certificate.revoked_at.is_none())
+            .skip(from_index)
+            .take(limit)
+            .map(|certificate| self.internal_certificate_token(certificate))
+            .collect()
+    }
+
+    pub fn nft_supply_for_owner(&self, account_id: AccountId) -> U128 {
+        U128(self.internal_owner_tokens(&account_id).count() as u128)
+    }
+
+    pub fn nft_tokens_for_owner(
+        &self,
+        account_id: AccountId,
+        from_index: Option<U128>,
+        limit: Option<u64>,
+    ) -> Vec<NftToken> {
+        let (from_index, limit) = page_bounds(
+            from_index.map(|index| index.0 as u64),
+            limit,
+            MAX_PAGE_LIMIT,
+        );
+
+        self.internal_owner_tokens(&account_id)
+            .skip(from_index)
+            .take(limit)
+            .map(|certificate| self.internal_certificate_token(certificate))
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Mints the certificate of a completed enrollment and links it from the
+    /// student's `certifications`. Does nothing if one was already issued.
+    pub(crate) fn internal_mint_certificate(&mut self, enrollment: &Enrollment, course: &Course) {
+        if self.enrollment_certificates.contains_key(&enrollment.id) {
+            log_str("A certificate was already issued for this enrollment");
+            return;
+        }
+
+        let completed_at = enrollment.completed_at.unwrap_or_else(env::block_timestamp);
+
+        // snapshot the quizz results the course was completed with
+        let mut quizzes_total: u32 = 0;
+        let mut quizzes_passed: u32 = 0;
+        let mut quizz_attempts: u32 = 0;
+        for module in self.get_course_modules(course.id).iter() {
+            if let Some(quizz_id) = module.quizz_id {
+                quizzes_total += 1;
+                let quizz_progress =
+                    self.get_quizz_progress(quizz_id, enrollment.student_id.clone());
+                if let Some(quizz_progress) = quizz_progress {
+                    if quizz_progress.is_correct {
+                        quizzes_passed += 1;
+                    }
+                    quizz_attempts += quizz_progress.try_count as u32;
+                }
+            }
+        }
+
+        let certificate = Certificate {
+            id: self.certificate_count,
+            course_id: course.id,
+            enrollment_id: enrollment.id,
+            mentor_id: course.mentor_id.clone(),
+            student_id: enrollment.student_id.clone(),
+            title: course.title.clone(),
+            picture: course.picture.clone(),
+            completed_at,
+            quizzes_total,
+            quizzes_passed,
+            quizz_attempts,
+            revoked_at: None,
+            revoked_by: None,
+            revocation_reason: None,
+        };
+
+        // add the certificate to the certificates map and its indexes
+        self.certificates
+            .insert(certificate.id, certificate.clone());
+        self.enrollment_certificates
+            .insert(enrollment.id, certificate.id);
+        self.student_certificates
+            .entry(certificate.student_id.clone())
+            .or_default()
+            .push(certificate.id);
+
+        // Increment the certificate count
+        self.certificate_count += 1;
+
+        // link the certificate from the student's profile
+        if let Some(mut student) = self.get_user_by_id(certificate.student_id.clone()) {
+            student
+                .certifications
+                .get_or_insert_with(Vec::new)
+                .push(Certification {
+                    id: certificate.id,
+                    title: certificate.title.clone(),
+                    from: certificate.mentor_id.to_string(),
+                    to: certificate.student_id.to_string(),
+                    picture: certificate.picture.clone(),
+                    created_at: completed_at,
+                    updated_at: completed_at,
+                });
+            self.internal_save_user(student);
+        }
+
+        // NEP-171 mint event
+        log_str(&format!(
+            "EVENT_JSON:{}",
+            json!({
+                "standard": "nep171",
+                "version": "1.0.0",
+                "event": "nft_mint",
+                "data": [{
+                    "owner_id": certificate.student_id,
+                    "token_ids": [certificate.id.to_string()],
+                }],
+            })
+        ));
+    }
+
+    /// Certificates of `account_id` that are still live tokens. Revoked ones
+    /// stay in `student_certificates` for `get_certificates_of`.
+    fn internal_owner_tokens<'a>(
+        &'a self,
+        account_id: &AccountId,
+    ) -> impl Iterator<Item = &'a Certificate> + 'a {
+        self.student_certificates
+            .get(account_id)
+            .into_iter()
+            .flatten()
+            .filter_map(|certificate_id| self.certificates.get(certificate_id))
+            .filter(|certificate| certificate.revoked_at.is_none())
+    }
+
+    fn internal_certificate_token(&self, certificate: &Certificate) -> NftToken {
+        NftToken {
+            token_id: certificate.id.to_string(),
+            owner_id: certificate.student_id.clone(),
+            metadata: TokenMetadata {
+                title: Some(format!("{} certificate", certificate.title)),
+                description: Some(format!(
+                    "Awarded to {} for completing {}, mentored by {}",
+                    certificate.student_id, certificate.title, certificate.mentor_id
+                )),
+                media: Some(certificate.picture.clone()).filter(|picture| !picture.is_empty()),
+                media_hash: None,
+                copies: Some(1),
+                // NEP-177 times are in milliseconds
+                issued_at: Some(certificate.completed_at / 1_000_000),
+                expires_at: None,
+                starts_at: None,
+                updated_at: None,
+                extra: Some(
+                    json!({
+                        "course_id": certificate.course_id,
+                        "enrollment_id": certificate.enrollment_id,
+                        "mentor_id": certificate.mentor_id,
+                        "student_id": certificate.student_id,
+                        "completed_at": certificate.completed_at,
+                        "revoked_at": certificate.revoked_at,
+                    })
+                    .to_string(),
+                ),
+                reference: None,
+                reference_hash: None,
+            },
+        }
+    }
+}
@@ -0,0 +1,220 @@
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::{near_bindgen, AccountId};
+
+#[near_bindgen]
+impl Contract {
+    pub fn get_student_progress_report(
+        &self,
+        account_id: AccountId,
+        course_id: u64,
+    ) -> Option<StudentProgressReport> {
+        let enrollment = self.get_enrollment(course_id, account_id.clone())?;
+
+        let mut modules: Vec<ModuleProgressReport> = vec![];
+        let mut completed_lessons: u32 = 0;
+        let mut total_lessons: u32 = 0;
+        let mut completed_quizzes: u32 = 0;
+        let mut total_quizzes: u32 = 0;
+
+        for module in self.get_course_modules(course_id).iter() {
+            let mut module_completed_lessons: u32 = 0;
+            for lesson_id in module.lessons_ids.iter() {
+                if self.is_student_lesson_completed(*lesson_id, account_id.clone()) {
+                    module_completed_lessons += 1;
+                }
+            }
+            let module_total_lessons = module.lessons_ids.len() as u32;
+
+            // a quizz counts once it is passed
+            let quizz_progress = module
+                .quizz_id
+                .and_then(|quizz_id| self.get_quizz_progress(quizz_id, account_id.clone()));
+            let quizz_passed = quizz_progress
+                .as_ref()
+                .is_some_and(|quizz_progress| quizz_progress.status == QuizzStatus::Completed);
+
+            let module_items = module_total_lessons + module.quizz_id.is_some() as u32;
+            let module_completed_items = module_completed_lessons + quizz_passed as u32;
+
+            let status = self
+                .get_module_progress(module.id, account_id.clone())
+                .map(|module_progress| module_progress.status)
+                .unwrap_or(ProgressStatus::NotStarted);
+
+            modules.push(ModuleProgressReport {
+                module_id: module.id,
+                title: module.title.clone(),
+                status,
+                progress: calculate_percentage(module_completed_items, module_items),
+                completed_lessons: module_completed_lessons,
+                total_lessons: module_total_lessons,
+                quizz_id: module.quizz_id,
+                quizz_status: quizz_progress.map(|quizz_progress| quizz_progress.status),
+                quizz_passed,
+            });
+
+            completed_lessons += module_completed_lessons;
+            total_lessons += module_total_lessons;
+            completed_quizzes += quizz_passed as u32;
+            total_quizzes += module.quizz_id.is_some() as u32;
+        }
+
+        Some(StudentProgressReport {
+            course_id,
+            student_id: account_id,
+            enrollment_status: enrollment.status,
+            progress: calculate_percentage(
+                completed_lessons + completed_quizzes,
+                total_lessons + total_quizzes,
+            ),
+            completed_lessons,
+            total_lessons,
+            completed_quizzes,
+            total_quizzes,
+            modules,
+        })
+    }
+}
+
+impl Contract {
+    /// Recomputes and stores the weighted progress of every module of the
+    /// course and of the enrollment itself.
+    pub(crate) fn internal_update_progress(&mut self, course_id: u64, account_id: &AccountId) {
+        let report = self.get_student_progress_report(account_id.clone(), course_id);
+        if report.is_none() {
+            return;
+        }
+        let report = report.unwrap();
+
+        for module_report in report.modules.iter() {
+            let key = (module_report.module_id, account_id.clone());
+            if let Some(module_progress) = self.module_progresses.get_mut(&key) {
+                module_progress.progress = module_report.progress;
+            }
+        }
+
+        if let Some(mut enrollment) = self.get_enrollment(course_id, account_id.clone()) {
+            enrollment.progress = report.progress;
+            self.enrollments.insert(enrollment.id, enrollment);
+        }
+    }
+
+    /// Returns the student's progress on a module, creating a not started
+    /// row if the module was added after the student enrolled. `None` when
+    /// the module does not exist or the student is not enrolled in its course.
+    pub(crate) fn internal_module_progress(
+        &mut self,
+        module_id: u64,
+        account_id: &AccountId,
+    ) -> Option<ModuleProgress> {
+        if let Some(module_progress) = self.get_module_progress(module_id, account_id.clone()) {
+            return Some(module_progress);
+        }
+
+        let module = self.get_module_by_id(module_id)?;
+        if !self.internal_is_course_enrolled(module.course_id, account_id) {
+            return None;
+        }
+
+        let new_module_progress = ModuleProgress {
+            id: self.module_progress_count,
+            module_id,
+            student_id: account_id.clone(),
+            status: ProgressStatus::NotStarted,
+            is_enrolled: true,
+            progress: 0,
+            completed_at: None,
+        };
+        self.module_progresses
+            .insert((module_id, account_id.clone()), new_module_progress.clone());
+        self.module_progress_count += 1;
+
+        Some(new_module_progress)
+    }
+
+    /// Lesson counterpart of `internal_module_progress`.
+    pub(crate) fn internal_lesson_progress(
+        &mut self,
+        lesson_id: u64,
+        account_id: &AccountId,
+    ) -> Option<LessonProgress> {
+        if let Some(lesson_progress) = self.get_lesson_progress(lesson_id, account_id.clone()) {
+            return Some(lesson_progress);
+        }
+
+        let lesson = self.get_lesson_by_id(lesson_id)?;
+        let module = self.get_module_by_id(lesson.module_id)?;
+        if !self.internal_is_course_enrolled(module.course_id, account_id) {
+            return None;
+        }
+
+        let new_lesson_progress = LessonProgress {
+            id: self.lesson_progress_count,
+            lesson_id,
+            student_id: account_id.clone(),
+            status: ProgressStatus::NotStarted,
+            is_enrolled: true,
+            completed_at: None,
+        };
+        self.lesson_progresses
+            .insert((lesson_id, account_id.clone()), new_lesson_progress.clone());
+        self.lesson_progress_count += 1;
+
+        Some(new_lesson_progress)
+    }
+
+    /// Quizz counterpart of `internal_module_progress`.
+    pub(crate) fn internal_quizz_progress(
+        &mut self,
+        quizz_id: u64,
+        account_id: &AccountId,
+    ) -> Option<QuizzProgress> {
+        if let Some(quizz_progress) = self.get_quizz_progress(quizz_id, account_id.clone()) {
+            return Some(quizz_progress);
+        }
+
+        let quizz = self.internal_get_quizz(quizz_id)?;
+        let module = self.get_module_by_id(quizz.module_id)?;
+        if !self.internal_is_course_enrolled(module.course_id, account_id) {
+            return None;
+        }
+
+        let new_quizz_progress = QuizzProgress {
+            id: self.quizz_progress_count,
+            quizz_id,
+            student_id: account_id.clone(),
+            status: QuizzStatus::NotStarted,
+            try_count: 0,
+            is_enrolled: true,
+            is_submitted: false,
+            is_correct: false,
+            completed_at: None,
+        };
+        self.quizz_progresses
+            .insert((quizz_id, account_id.clone()), new_quizz_progress.clone());
+        self.quizz_progress_count += 1;
+
+        Some(new_quizz_progress)
+    }
+
+    /// Enrolled and graduated students can both work through the course.
+    pub(crate) fn internal_is_course_enrolled(
+        &self,
+        course_id: u64,
+        account_id: &AccountId,
+    ) -> bool {
+        self.get_enrollment(course_id, account_id.clone())
+            .is_some_and(|enrollment| {
+                enrollment.status == EnrollmentStatus::Enrolled
+                    || enrollment.status == EnrollmentStatus::Completed
+            })
+    }
+}
+
+fn calculate_percentage(completed: u32, total: u32) -> u16 {
+    if total == 0 {
+        return 0;
+    }
+    (completed * 100 / total) as u16
+}
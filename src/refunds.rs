@@ -0,0 +1,615 @@
+use crate::earnings::GAS_FOR_FT_TRANSFER;
+use crate::errors::ContractError;
+use crate::events::*;
+use crate::getters::{page_bounds, MAX_PAGE_LIMIT};
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::json;
+use near_sdk::{env, is_promise_success, near_bindgen, AccountId, Gas, Promise};
+
+/// Refunds are allowed for 7 days after payment by default.
+pub const DEFAULT_REFUND_WINDOW_SECONDS: u64 = 7 * 24 * 60 * 60;
+
+/// Refunds are allowed until 20% of the lessons are completed by default.
+pub const DEFAULT_REFUND_MAX_PROGRESS: u16 = 20;
+
+const NANOS_PER_SECOND: u64 = 1_000_000_000;
+
+/// Gas attached to the callback that settles a refund transfer.
+const GAS_FOR_RESOLVE_REFUND: Gas = Gas(20_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    #[handle_result]
+    pub fn set_refund_policy(
+        &mut self,
+        window_seconds: u64,
+        max_progress: u16,
+    ) -> Result<bool, ContractError> {
+        let owner_id =
+            self.require_role(Role::Owner, "Only the owner can change the refund policy")?;
+        let initial_storage = env::storage_usage();
+
+        if max_progress > 100 {
+            return Err(ContractError::InvalidInput(
+                "The maximum progress cannot exceed 100".to_string(),
+            ));
+        }
+
+        self.refund_policy = RefundPolicy {
+            window_seconds,
+            max_progress,
+        };
+
+        log_str(&format!(
+            "Refund policy changed: {} seconds, up to {}% progress",
+            window_seconds, max_progress
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn get_refund_policy(&self) -> RefundPolicy {
+        self.refund_policy.clone()
+    }
+
+    pub fn get_enrollment_payment(&self, enrollment_id: u64) -> Option<EnrollmentPayment> {
+        self.enrollment_payments.get(&enrollment_id).cloned()
+    }
+
+    pub fn get_mentor_escrowed_payments(
+        &self,
+        mentor_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<EnrollmentPayment> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let mut payment_list: Vec<EnrollmentPayment> = vec![];
+        if let Some(enrollment_ids) = self.mentor_escrows.get(&mentor_id) {
+            for enrollment_id in enrollment_ids.iter().skip(from_index).take(limit) {
+                if let Some(payment) = self.enrollment_payments.get(enrollment_id) {
+                    payment_list.push(payment.clone());
+                }
+            }
+        }
+        payment_list
+    }
+
+    /// Applies to enrollments paid after it is set.
+    #[handle_result]
+    pub fn set_course_escrow_policy(
+        &mut self,
+        course_id: u64,
+        upfront_percent: u16,
+        milestone_percent: u16,
+        timeout_seconds: u64,
+    ) -> Result<bool, ContractError> {
+        let admin_id = self.require_role(Role::Admin, "Only admins can set escrow policies")?;
+        let initial_storage = env::storage_usage();
+
+        // check if the course exists
+        if self.get_course_by_id(course_id).is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+
+        if upfront_percent > 100 || milestone_percent > 100 {
+            return Err(ContractError::InvalidInput(
+                "Escrow percentages cannot exceed 100".to_string(),
+            ));
+        }
+
+        self.course_escrow_policies.insert(
+            course_id,
+            CourseEscrowPolicy {
+                upfront_percent,
+                milestone_percent,
+                timeout_seconds,
+            },
+        );
+
+        log_str(&format!(
+            "Escrow policy of course {}: {}% upfront, rest at {}% of lessons or after {} seconds",
+            course_id, upfront_percent, milestone_percent, timeout_seconds
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&admin_id, initial_storage);
+
+        Ok(true)
+    }
+
+    #[handle_result]
+    pub fn remove_course_escrow_policy(&mut self, course_id: u64) -> Result<bool, ContractError> {
+        let admin_id = self.require_role(Role::Admin, "Only admins can remove escrow policies")?;
+        let initial_storage = env::storage_usage();
+
+        if self.course_escrow_policies.remove(&course_id).is_none() {
+            return Err(ContractError::NotFound(
+                "Course has no escrow policy".to_string(),
+            ));
+        }
+
+        // release the storage freed by the caller
+        self.charge_storage(&admin_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn get_course_escrow_policy(&self, course_id: u64) -> Option<CourseEscrowPolicy> {
+        self.course_escrow_policies.get(&course_id).cloned()
+    }
+
+    /// Whether the signer could get their payment for `course_id` back now.
+    pub fn is_refundable(&self, course_id: u64, account_id: AccountId) -> bool {
+        let enrollment = self.get_enrollment(course_id, account_id);
+        if enrollment.is_none() {
+            return false;
+        }
+        let enrollment = enrollment.unwrap();
+        self.enrollment_payments
+            .get(&enrollment.id)
+            .is_some_and(|payment| self.internal_is_refundable(&enrollment, payment))
+    }
+
+    #[handle_result]
+    pub fn request_refund(&mut self, course_id: u64) -> Result<bool, ContractError> {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the student is enrolled in the course
+        let enrollment = self.get_enrollment(course_id, account_id.clone());
+        if enrollment.is_none() {
+            return Err(ContractError::NotFound(
+                "Student is not enrolled in the course".to_string(),
+            ));
+        }
+        let mut enrollment = enrollment.unwrap();
+
+        // enrollments paid before escrow existed have no payment record
+        let payment = self.enrollment_payments.get(&enrollment.id).cloned();
+        if payment.is_none() {
+            return Err(ContractError::NotFound(
+                "No refundable payment for this enrollment".to_string(),
+            ));
+        }
+        let mut payment = payment.unwrap();
+
+        if !self.internal_is_refundable(&enrollment, &payment) {
+            return Err(ContractError::InvalidState(
+                "The enrollment is outside the refund window or progress threshold".to_string(),
+            ));
+        }
+
+        // claw back what is still escrowed, the mentor keeps the upfront
+        // share and the fee has not left escrow yet
+        let escrowed = payment.escrowed.0;
+        let fee = payment.fee.0;
+        self.internal_claw_back_earnings(&payment.mentor_id, &payment.token_id, escrowed);
+        self.internal_remove_escrow(&payment);
+
+        // both stay refunding until `on_refund_resolved` sees the transfer
+        payment.status.transition_to(PaymentStatus::Refunding)?;
+        self.enrollment_payments
+            .insert(enrollment.id, payment.clone());
+
+        enrollment
+            .status
+            .transition_to(EnrollmentStatus::Refunding)?;
+        enrollment.updated_at = env::block_timestamp();
+        self.enrollments.insert(enrollment.id, enrollment.clone());
+
+        // give the student their payment back
+        let amount = escrowed + fee;
+        let transfer = match payment.token_id.clone() {
+            None => Promise::new(account_id.clone()).transfer(amount),
+            Some(token_id) => Promise::new(token_id).function_call(
+                "ft_transfer".to_string(),
+                json!({ "receiver_id": account_id, "amount": U128(amount) })
+                    .to_string()
+                    .into_bytes(),
+                1,
+                GAS_FOR_FT_TRANSFER,
+            ),
+        };
+        transfer.then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_REFUND)
+                .on_refund_resolved(enrollment.id),
+        );
+
+        log_str(&format!(
+            "Refunding {} to {} for course {}",
+            amount, account_id, course_id
+        ));
+
+        // emit the refund event
+        CourchainEvent::Refund(vec![RefundData {
+            enrollment_id: enrollment.id,
+            course_id,
+            student_id: account_id.clone(),
+            token_id: payment.token_id.clone(),
+            amount: U128(amount),
+            status: payment.status,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
+    }
+
+    /// Finishes a refund once its transfer settled. A failed transfer puts
+    /// the payment back in escrow and the student back in the course, the
+    /// restored bytes are booked to the student without a balance check: a
+    /// panic there would keep them from being restored.
+    #[private]
+    pub fn on_refund_resolved(&mut self, enrollment_id: u64) -> bool {
+        let payment = self.enrollment_payments.get(&enrollment_id).cloned();
+        let enrollment = self.enrollments.get(&enrollment_id).cloned();
+        if payment.is_none() || enrollment.is_none() {
+            log_str(&format!(
+                "Refund of enrollment {} does not exist",
+                enrollment_id
+            ));
+            return false;
+        }
+        let mut payment = payment.unwrap();
+        let mut enrollment = enrollment.unwrap();
+
+        if is_promise_success() {
+            let initial_storage = env::storage_usage();
+
+            if let Err(error) = payment.status.transition_to(PaymentStatus::Refunded) {
+                log_str(&error.to_string());
+                return false;
+            }
+            payment.settled_at = Some(env::block_timestamp());
+            self.enrollment_payments
+                .insert(enrollment_id, payment.clone());
+
+            if let Err(error) = enrollment.status.transition_to(EnrollmentStatus::Refunded) {
+                log_str(&error.to_string());
+                return false;
+            }
+            // a refunded student no longer rates the course
+            if let Some(review_id) = enrollment.course_review.take() {
+                self.internal_remove_review(review_id);
+            }
+            enrollment.updated_at = env::block_timestamp();
+            self.enrollments.insert(enrollment_id, enrollment.clone());
+
+            // roll back the progress rows created at enrollment
+            self.internal_remove_progress_rows(enrollment.course_id, &enrollment.student_id);
+
+            log_str(&format!(
+                "Refunded {} to {} for course {}",
+                payment.escrowed.0 + payment.fee.0,
+                enrollment.student_id,
+                enrollment.course_id
+            ));
+
+            // emit the refund event
+            CourchainEvent::Refund(vec![RefundData {
+                enrollment_id,
+                course_id: enrollment.course_id,
+                student_id: enrollment.student_id.clone(),
+                token_id: payment.token_id.clone(),
+                amount: U128(payment.escrowed.0 + payment.fee.0),
+                status: payment.status,
+            }])
+            .emit();
+
+            // release the storage freed by the student
+            self.charge_storage(&enrollment.student_id, initial_storage);
+
+            return true;
+        }
+
+        // the transfer failed, put the sale back in the mentor's escrow
+        let student_storage = self.storage_checkpoint();
+        if let Err(error) = payment.status.transition_to(PaymentStatus::Escrowed) {
+            log_str(&error.to_string());
+            return false;
+        }
+        self.internal_escrow_earnings(&payment.mentor_id, &payment.token_id, payment.escrowed.0);
+        self.mentor_escrows
+            .entry(payment.mentor_id.clone())
+            .or_default()
+            .push(enrollment_id);
+        self.enrollment_payments
+            .insert(enrollment_id, payment.clone());
+
+        // and the student back in the course
+        if let Err(error) = enrollment.status.transition_to(EnrollmentStatus::Enrolled) {
+            log_str(&error.to_string());
+            return false;
+        }
+        enrollment.updated_at = env::block_timestamp();
+        self.enrollments.insert(enrollment_id, enrollment.clone());
+
+        // the student paid for these records until the refund released them
+        self.release_storage(&enrollment.student_id, student_storage, student_storage);
+
+        log_str(&format!(
+            "Refund to {} failed, enrollment {} restored",
+            enrollment.student_id, enrollment_id
+        ));
+
+        // emit the refund event
+        CourchainEvent::Refund(vec![RefundData {
+            enrollment_id,
+            course_id: enrollment.course_id,
+            student_id: enrollment.student_id.clone(),
+            token_id: payment.token_id.clone(),
+            amount: U128(payment.escrowed.0 + payment.fee.0),
+            status: payment.status,
+        }])
+        .emit();
+        false
+    }
+
+    /// Settles a page of the mentor's escrowed sales that are past the
+    /// refund window, booking their fee to the treasury, and releases those
+    /// past their escrow milestone or timeout. Anyone can call it, the signer
+    /// pays for the storage used. Released sales leave the escrow list, so
+    /// the next page starts lower. Returns how many sales changed.
+    pub fn release_escrow(
+        &mut self,
+        mentor_id: AccountId,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> u64 {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let enrollment_ids: Vec<u64> = self
+            .mentor_escrows
+            .get(&mentor_id)
+            .map(|enrollment_ids| {
+                enrollment_ids
+                    .iter()
+                    .skip(from_index)
+                    .take(limit)
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let mut released: u64 = 0;
+        for enrollment_id in enrollment_ids.iter() {
+            if self.internal_try_release_escrow(*enrollment_id) {
+                released += 1;
+            }
+        }
+
+        log_str(&format!(
+            "Settled or released {} escrowed sales for {}",
+            released, mentor_id
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        released
+    }
+}
+
+impl Contract {
+    /// Records a payment for a freshly enrolled course, releasing the
+    /// upfront share of the escrow policy to the mentor and escrowing the
+    /// rest. The platform fee is held with it until the payment settles.
+    pub(crate) fn internal_record_payment(
+        &mut self,
+        enrollment: &Enrollment,
+        course: &Course,
+        token_id: Option<AccountId>,
+        price: u128,
+        fee: u128,
+    ) {
+        let escrow_policy = self.course_escrow_policies.get(&course.id).cloned();
+        let upfront = escrow_policy
+            .as_ref()
+            .map(|policy| price * policy.upfront_percent as u128 / 100)
+            .unwrap_or(0);
+        self.internal_escrow_earnings(&course.mentor_id, &token_id, price);
+        if upfront > 0 {
+            self.internal_release_earnings(&course.mentor_id, &token_id, upfront);
+        }
+
+        let payment = EnrollmentPayment {
+            enrollment_id: enrollment.id,
+            course_id: course.id,
+            student_id: enrollment.student_id.clone(),
+            mentor_id: course.mentor_id.clone(),
+            token_id,
+            price: U128(price),
+            fee: U128(fee),
+            escrowed: U128(price - upfront),
+            released: U128(upfront),
+            escrow_policy,
+            status: PaymentStatus::Escrowed,
+            paid_at: env::block_timestamp(),
+            settled_at: None,
+        };
+        self.mentor_escrows
+            .entry(course.mentor_id.clone())
+            .or_default()
+            .push(enrollment.id);
+        self.enrollment_payments
+            .insert(enrollment.id, payment.clone());
+
+        if upfront > 0 {
+            // emit the escrow_released event
+            CourchainEvent::EscrowReleased(vec![EscrowReleasedData {
+                enrollment_id: enrollment.id,
+                mentor_id: payment.mentor_id,
+                token_id: payment.token_id,
+                fee: U128(0),
+                released: U128(upfront),
+                status: payment.status,
+            }])
+            .emit();
+        }
+    }
+
+    /// Settles a payment once it can no longer be refunded, booking its fee
+    /// to the treasury, and releases what is left in escrow once the policy
+    /// milestone or timeout is met.
+    /// Returns whether anything changed.
+    pub(crate) fn internal_try_release_escrow(&mut self, enrollment_id: u64) -> bool {
+        let payment = self.enrollment_payments.get(&enrollment_id).cloned();
+        let enrollment = self.enrollments.get(&enrollment_id).cloned();
+        if payment.is_none() || enrollment.is_none() {
+            return false;
+        }
+        let mut payment = payment.unwrap();
+        let enrollment = enrollment.unwrap();
+
+        let mut changed = false;
+        if payment.status == PaymentStatus::Escrowed {
+            if self.internal_is_refundable(&enrollment, &payment) {
+                return false;
+            }
+
+            // the refund window is closed, the fee belongs to the treasury
+            if payment
+                .status
+                .transition_to(PaymentStatus::Settled)
+                .is_err()
+            {
+                return false;
+            }
+            payment.settled_at = Some(env::block_timestamp());
+            self.internal_credit_fee(&payment);
+
+            self.enrollment_payments
+                .insert(enrollment_id, payment.clone());
+            changed = true;
+
+            // emit the escrow_released event
+            CourchainEvent::EscrowReleased(vec![EscrowReleasedData {
+                enrollment_id,
+                mentor_id: payment.mentor_id.clone(),
+                token_id: payment.token_id.clone(),
+                fee: payment.fee,
+                released: U128(0),
+                status: payment.status,
+            }])
+            .emit();
+        }
+
+        if !payment.status.can_transition_to(PaymentStatus::Released) {
+            return changed;
+        }
+
+        // nothing left in escrow once the whole price was released up front
+        if let Some(policy) = payment
+            .escrow_policy
+            .as_ref()
+            .filter(|_| payment.escrowed.0 > 0)
+        {
+            let progress = self.calculate_lessons_completed_percentage(
+                enrollment.course_id,
+                enrollment.student_id.clone(),
+            );
+            let timeout = policy.timeout_seconds.saturating_mul(NANOS_PER_SECOND);
+            let timed_out = env::block_timestamp() >= payment.paid_at.saturating_add(timeout);
+            if progress < policy.milestone_percent && !timed_out {
+                return changed;
+            }
+        }
+
+        if payment
+            .status
+            .transition_to(PaymentStatus::Released)
+            .is_err()
+        {
+            return changed;
+        }
+
+        let amount = payment.escrowed.0;
+        self.internal_release_earnings(&payment.mentor_id, &payment.token_id, amount);
+        self.internal_remove_escrow(&payment);
+
+        payment.released = U128(payment.released.0 + amount);
+        payment.escrowed = U128(0);
+        self.enrollment_payments
+            .insert(enrollment_id, payment.clone());
+
+        log_str(&format!(
+            "Released {} escrowed for enrollment {} to {}",
+            amount, enrollment_id, payment.mentor_id
+        ));
+
+        // emit the escrow_released event
+        CourchainEvent::EscrowReleased(vec![EscrowReleasedData {
+            enrollment_id,
+            mentor_id: payment.mentor_id.clone(),
+            token_id: payment.token_id.clone(),
+            fee: U128(0),
+            released: U128(amount),
+            status: payment.status,
+        }])
+        .emit();
+
+        true
+    }
+
+    /// A payment is refundable while it is escrowed, within the refund window
+    /// and while the student is below the progress threshold.
+    fn internal_is_refundable(&self, enrollment: &Enrollment, payment: &EnrollmentPayment) -> bool {
+        if payment.status != PaymentStatus::Escrowed
+            || enrollment.status != EnrollmentStatus::Enrolled
+        {
+            return false;
+        }
+
+        let window = self
+            .refund_policy
+            .window_seconds
+            .saturating_mul(NANOS_PER_SECOND);
+        if env::block_timestamp() > payment.paid_at.saturating_add(window) {
+            return false;
+        }
+
+        let progress = self.calculate_lessons_completed_percentage(
+            enrollment.course_id,
+            enrollment.student_id.clone(),
+        );
+        progress <= self.refund_policy.max_progress
+    }
+
+    fn internal_remove_escrow(&mut self, payment: &EnrollmentPayment) {
+        if let Some(enrollment_ids) = self.mentor_escrows.get_mut(&payment.mentor_id) {
+            enrollment_ids.retain(|id| *id != payment.enrollment_id);
+            if enrollment_ids.is_empty() {
+                self.mentor_escrows.remove(&payment.mentor_id);
+            }
+        }
+    }
+
+    /// Deletes the module, quizz and lesson progress rows of a student for
+    /// every module of the course.
+    pub(crate) fn internal_remove_progress_rows(&mut self, course_id: u64, account_id: &AccountId) {
+        for module in self.get_course_modules(course_id).iter() {
+            self.module_progresses
+                .remove(&(module.id, account_id.clone()));
+            if let Some(quizz_id) = module.quizz_id {
+                self.quizz_progresses
+                    .remove(&(quizz_id, account_id.clone()));
+            }
+            for lesson_id in module.lessons_ids.iter() {
+                self.lesson_progresses
+                    .remove(&(*lesson_id, account_id.clone()));
+            }
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use crate::errors::ContractError;
+use crate::events::*;
+use crate::getters::{page_bounds, MAX_PAGE_LIMIT};
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::{env, near_bindgen, AccountId};
+
+#[near_bindgen]
+impl Contract {
+    #[handle_result]
+    pub fn submit_review(
+        &mut self,
+        course_id: u64,
+        rating: u8,
+        text: String,
+        client_created_at: Option<u64>,
+    ) -> Result<bool, ContractError> {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let created_at = env::block_timestamp();
+
+        // check if the rating is between 1 and 5
+        if !(1..=5).contains(&rating) {
+            return Err(ContractError::InvalidInput(
+                "Rating must be between 1 and 5".to_string(),
+            ));
+        }
+
+        // check if the course exists
+        let course = self.get_course_by_id(course_id);
+        if course.is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+
+        // only enrolled or graduated students can review the course
+        let enrollment = self.get_enrollment(course_id, account_id.clone());
+        if enrollment.is_none() {
+            return Err(ContractError::Unauthorized(
+                "Student is not enrolled in the course".to_string(),
+            ));
+        }
+        let mut enrollment = enrollment.unwrap();
+        if enrollment.status != EnrollmentStatus::Enrolled
+            && enrollment.status != EnrollmentStatus::Completed
+        {
+            return Err(ContractError::Unauthorized(
+                "Student is not enrolled in the course".to_string(),
+            ));
+        }
+
+        // one review per student and course, whichever enrollment it is
+        if self
+            .student_reviews
+            .contains_key(&(course_id, account_id.clone()))
+        {
+            return Err(ContractError::AlreadyExists(
+                "Course is already reviewed by the student".to_string(),
+            ));
+        }
+
+        let new_review: Review = Review {
+            id: self.review_count,
+            course_id,
+            enrollment_id: enrollment.id,
+            student_id: account_id.clone(),
+            rating,
+            text,
+            mentor_reply: None,
+            replied_at: None,
+            is_hidden: false,
+            hidden_by: None,
+            created_at,
+            updated_at: created_at,
+            client_created_at,
+        };
+
+        // add review to the reviews map and its course index
+        self.reviews.insert(new_review.id, new_review.clone());
+        self.course_reviews
+            .entry(course_id)
+            .or_default()
+            .push(new_review.id);
+        self.student_reviews
+            .insert((course_id, account_id.clone()), new_review.id);
+
+        // Increment the review count
+        self.review_count += 1;
+
+        // link the review from the enrollment
+        enrollment.course_review = Some(new_review.id);
+        self.enrollments.insert(enrollment.id, enrollment);
+
+        // add the rating to the course average
+        self.internal_update_course_rating(course_id, rating, true);
+
+        log_str(&format!(
+            "Course '{}' reviewed by {}",
+            course.unwrap().title,
+            account_id
+        ));
+
+        // emit the review_submitted event
+        CourchainEvent::ReviewSubmitted(vec![ReviewSubmittedData {
+            review_id: new_review.id,
+            course_id,
+            student_id: account_id.clone(),
+            rating,
+            created_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
+    }
+
+    #[handle_result]
+    pub fn reply_to_review(
+        &mut self,
+        review_id: u64,
+        reply: String,
+    ) -> Result<bool, ContractError> {
+        let account_id: AccountId = env::signer_account_id();
+        let initial_storage = env::storage_usage();
+        let replied_at = env::block_timestamp();
+
+        // check if the review exists
+        let review = self.reviews.get(&review_id).cloned();
+        if review.is_none() {
+            return Err(ContractError::NotFound("Review does not exist".to_string()));
+        }
+        let mut review = review.unwrap();
+
+        // only the mentor of the course can reply
+        let course = self.get_course_by_id(review.course_id);
+        if course.is_none() || course.unwrap().mentor_id != account_id {
+            return Err(ContractError::Unauthorized(
+                "Only the mentor of the course can reply to its reviews".to_string(),
+            ));
+        }
+
+        review.mentor_reply = Some(reply);
+        review.replied_at = Some(replied_at);
+        review.updated_at = replied_at;
+
+        // update the review
+        self.reviews.insert(review_id, review.clone());
+
+        log_str(&format!("Mentor replied to review: {}", review_id));
+
+        // emit the review_replied event
+        CourchainEvent::ReviewReplied(vec![ReviewRepliedData {
+            review_id,
+            course_id: review.course_id,
+            mentor_id: account_id.clone(),
+            replied_at,
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
+    }
+
+    /// Hides an abusive review from course listings and drops its rating from
+    /// the course average.
+    #[handle_result]
+    pub fn hide_review(&mut self, review_id: u64) -> Result<bool, ContractError> {
+        let moderator_id =
+            self.require_role(Role::Moderator, "Only moderators can hide reviews")?;
+        let initial_storage = env::storage_usage();
+
+        // check if the review exists
+        let review = self.reviews.get(&review_id).cloned();
+        if review.is_none() {
+            return Err(ContractError::NotFound("Review does not exist".to_string()));
+        }
+        let mut review = review.unwrap();
+
+        if review.is_hidden {
+            return Err(ContractError::InvalidState(
+                "Review is already hidden".to_string(),
+            ));
+        }
+
+        review.is_hidden = true;
+        review.hidden_by = Some(moderator_id.clone());
+
+        // update the review
+        self.reviews.insert(review_id, review.clone());
+
+        // remove the rating from the course average
+        self.internal_update_course_rating(review.course_id, review.rating, false);
+
+        log_str(&format!("Review {} hidden by {}", review_id, moderator_id));
+
+        // emit the review_hidden event
+        CourchainEvent::ReviewHidden(vec![ReviewHiddenData {
+            review_id,
+            course_id: review.course_id,
+            hidden_by: moderator_id.clone(),
+        }])
+        .emit();
+
+        // charge the caller for the storage used
+        self.charge_storage(&moderator_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn get_review_by_id(&self, review_id: u64) -> Option<Review> {
+        self.reviews.get(&review_id).cloned()
+    }
+
+    /// Pages over the visible reviews, hidden ones are skipped.
+    pub fn get_course_reviews(
+        &self,
+        course_id: u64,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<Review> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let mut review_list: Vec<Review> = vec![];
+        if let Some(review_ids) = self.course_reviews.get(&course_id) {
+            let reviews = review_ids
+                .iter()
+                .filter_map(|review_id| self.reviews.get(review_id))
+                .filter(|review| !review.is_hidden);
+            for review in reviews.skip(from_index).take(limit) {
+                review_list.push(review.clone());
+            }
+        }
+        review_list
+    }
+}
+
+impl Contract {
+    /// Deletes the review of a refunded enrollment, taking its rating out of
+    /// the course average, so only paying students rate a course.
+    pub(crate) fn internal_remove_review(&mut self, review_id: u64) {
+        let review = self.reviews.remove(&review_id);
+        if review.is_none() {
+            return;
+        }
+        let review = review.unwrap();
+
+        if let Some(review_ids) = self.course_reviews.get_mut(&review.course_id) {
+            review_ids.retain(|id| *id != review_id);
+        }
+        self.student_reviews
+            .remove(&(review.course_id, review.student_id.clone()));
+
+        // hidden reviews are already out of the average
+        if !review.is_hidden {
+            self.internal_update_course_rating(review.course_id, review.rating, false);
+        }
+
+        // emit the review_removed event
+        CourchainEvent::ReviewRemoved(vec![ReviewRemovedData {
+            review_id,
+            course_id: review.course_id,
+            student_id: review.student_id,
+        }])
+        .emit();
+    }
+
+    /// Adds or removes one rating from the running totals of a course.
+    fn internal_update_course_rating(&mut self, course_id: u64, rating: u8, add: bool) {
+        let course = self.courses.get(&course_id).cloned();
+        if course.is_none() {
+            return;
+        }
+
+        let mut course: Course = course.unwrap();
+        if add {
+            course.rating_count += 1;
+            course.rating_sum += rating as u64;
+        } else {
+            course.rating_count = course.rating_count.saturating_sub(1);
+            course.rating_sum = course.rating_sum.saturating_sub(rating as u64);
+        }
+        course.average_rating = if course.rating_count == 0 {
+            0.0
+        } else {
+            course.rating_sum as f32 / course.rating_count as f32
+        };
+        // update the course
+        self.courses.insert(course_id, course);
+    }
+}
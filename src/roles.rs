@@ -0,0 +1,169 @@
+use crate::errors::ContractError;
+use crate::getters::{page_bounds, MAX_PAGE_LIMIT};
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::{env, near_bindgen, AccountId, FunctionError};
+
+#[near_bindgen]
+impl Contract {
+    #[handle_result]
+    pub fn grant_role(&mut self, account_id: AccountId, role: Role) -> Result<bool, ContractError> {
+        // only holders of the managing role can grant this role
+        let caller_id = self.require_role(
+            role.managed_by(),
+            "Caller is not allowed to grant this role",
+        )?;
+        let initial_storage = env::storage_usage();
+
+        if !self.internal_grant_role(account_id.clone(), role) {
+            return Err(ContractError::AlreadyExists(format!(
+                "Account {} already has role {:?}",
+                account_id, role
+            )));
+        }
+
+        log_str(&format!("Role {:?} granted to {}", role, account_id));
+
+        // charge the caller for the storage used
+        self.charge_storage(&caller_id, initial_storage);
+
+        Ok(true)
+    }
+
+    #[handle_result]
+    pub fn revoke_role(
+        &mut self,
+        account_id: AccountId,
+        role: Role,
+    ) -> Result<bool, ContractError> {
+        // only holders of the managing role can revoke this role
+        let caller_id = self.require_role(
+            role.managed_by(),
+            "Caller is not allowed to revoke this role",
+        )?;
+        let initial_storage = env::storage_usage();
+
+        // the contract owner always keeps the owner role
+        if account_id == self.owner_id && role == Role::Owner {
+            return Err(ContractError::InvalidInput(
+                "The contract owner cannot lose the owner role".to_string(),
+            ));
+        }
+
+        let mut roles = self.get_account_roles(account_id.clone());
+        let index = roles.iter().position(|r| *r == role);
+        if index.is_none() {
+            return Err(ContractError::NotFound(format!(
+                "Account {} does not have role {:?}",
+                account_id, role
+            )));
+        }
+        roles.remove(index.unwrap());
+
+        if roles.is_empty() {
+            self.roles.remove(&account_id);
+        } else {
+            self.roles.insert(account_id.clone(), roles);
+        }
+
+        log_str(&format!("Role {:?} revoked from {}", role, account_id));
+
+        // release the storage freed by the caller
+        self.charge_storage(&caller_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn has_role(&self, account_id: AccountId, role: Role) -> bool {
+        if account_id == self.owner_id {
+            return true;
+        }
+
+        self.get_account_roles(account_id)
+            .iter()
+            .any(|r| r.implies(role))
+    }
+
+    pub fn get_account_roles(&self, account_id: AccountId) -> Vec<Role> {
+        let mut roles = self.roles.get(&account_id).cloned().unwrap_or_default();
+        if account_id == self.owner_id && !roles.contains(&Role::Owner) {
+            roles.insert(0, Role::Owner);
+        }
+        roles
+    }
+
+    /// Accounts granted `role` itself, the owner first for `Role::Owner`.
+    pub fn get_role_members(
+        &self,
+        role: Role,
+        from_index: Option<u64>,
+        limit: Option<u64>,
+    ) -> Vec<AccountId> {
+        let (from_index, limit) = page_bounds(from_index, limit, MAX_PAGE_LIMIT);
+
+        let owner = Some(self.owner_id.clone()).filter(|_| role == Role::Owner);
+        let granted = self
+            .roles
+            .iter()
+            .filter(|(account_id, roles)| {
+                roles.contains(&role) && owner.as_ref() != Some(account_id)
+            })
+            .map(|(account_id, _)| account_id.clone());
+        owner
+            .clone()
+            .into_iter()
+            .chain(granted)
+            .skip(from_index)
+            .take(limit)
+            .collect()
+    }
+}
+
+impl Contract {
+    /// Single guard for every privileged method: panics unless the signer
+    /// holds `role` (or a role implying it) and returns the signer otherwise.
+    pub(crate) fn assert_role(&self, role: Role, message: &str) -> AccountId {
+        self.require_role(role, message)
+            .unwrap_or_else(|error| error.panic())
+    }
+
+    /// `assert_role` for `#[handle_result]` methods.
+    pub(crate) fn require_role(
+        &self,
+        role: Role,
+        message: &str,
+    ) -> Result<AccountId, ContractError> {
+        let account_id: AccountId = env::signer_account_id();
+        if !self.has_role(account_id.clone(), role) {
+            return Err(ContractError::Unauthorized(message.to_string()));
+        }
+        Ok(account_id)
+    }
+
+    /// Guard of the course content methods: the signer must be the mentor of
+    /// `course` and still hold the mentor role, or be an admin.
+    pub(crate) fn require_course_mentor(
+        &self,
+        course: &Course,
+        message: &str,
+    ) -> Result<AccountId, ContractError> {
+        let account_id: AccountId = env::signer_account_id();
+        let is_mentor =
+            course.mentor_id == account_id && self.has_role(account_id.clone(), Role::Mentor);
+        if !is_mentor && !self.has_role(account_id.clone(), Role::Admin) {
+            return Err(ContractError::Unauthorized(message.to_string()));
+        }
+        Ok(account_id)
+    }
+
+    pub(crate) fn internal_grant_role(&mut self, account_id: AccountId, role: Role) -> bool {
+        let mut roles = self.roles.get(&account_id).cloned().unwrap_or_default();
+        if roles.contains(&role) {
+            return false;
+        }
+        roles.push(role);
+        self.roles.insert(account_id, roles);
+        true
+    }
+}
@@ -0,0 +1,337 @@
+use crate::errors::ContractError;
+use crate::migrations::write_state_version;
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::{assert_one_yocto, env, near_bindgen, AccountId, FunctionError, Promise};
+
+/// Bytes taken by one entry of `storage_accounts`: 40 bytes of record
+/// overhead, the prefixed key (1 + 4 + up to 64 bytes) and the 24 byte value.
+/// The cost of these bytes is the minimum balance and is never withdrawable.
+pub const REGISTRATION_STORAGE_BYTES: u64 = 133;
+
+#[near_bindgen]
+impl Contract {
+    #[payable]
+    pub fn storage_deposit(
+        &mut self,
+        account_id: Option<AccountId>,
+        registration_only: Option<bool>,
+    ) -> StorageBalance {
+        let amount = env::attached_deposit();
+        let account_id = account_id.unwrap_or_else(env::predecessor_account_id);
+        let registration_only = registration_only.unwrap_or(false);
+        let min_balance = self.storage_balance_bounds().min.0;
+
+        let storage_account = self.storage_accounts.get(&account_id).cloned();
+        if let Some(mut storage_account) = storage_account {
+            if registration_only {
+                // already registered, give the whole deposit back
+                log_str("Account is already registered");
+                if amount > 0 {
+                    Promise::new(env::predecessor_account_id()).transfer(amount);
+                }
+            } else {
+                storage_account.total += amount;
+                self.storage_accounts
+                    .insert(account_id.clone(), storage_account);
+            }
+        } else {
+            if amount < min_balance {
+                log_str("The attached deposit is less than the minimum storage balance");
+                env::panic_str("The attached deposit is less than the minimum storage balance")
+            }
+
+            let total = if registration_only {
+                let refund = amount - min_balance;
+                if refund > 0 {
+                    Promise::new(env::predecessor_account_id()).transfer(refund);
+                }
+                min_balance
+            } else {
+                amount
+            };
+
+            self.storage_accounts.insert(
+                account_id.clone(),
+                StorageAccount {
+                    total,
+                    used_bytes: 0,
+                },
+            );
+
+            log_str(&format!("Storage account registered: {}", account_id));
+        }
+
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    #[payable]
+    pub fn storage_withdraw(&mut self, amount: Option<U128>) -> StorageBalance {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+
+        let balance = self.storage_balance_of(account_id.clone());
+        if balance.is_none() {
+            log_str("Account is not registered");
+            env::panic_str("Account is not registered")
+        }
+
+        let available = balance.unwrap().available.0;
+        let amount = amount.map(u128::from).unwrap_or(available);
+        if amount > available {
+            log_str("The amount is greater than the available storage balance");
+            env::panic_str("The amount is greater than the available storage balance")
+        }
+
+        if amount > 0 {
+            let mut storage_account = self.storage_accounts.get(&account_id).cloned().unwrap();
+            storage_account.total -= amount;
+            self.storage_accounts
+                .insert(account_id.clone(), storage_account);
+
+            Promise::new(account_id.clone()).transfer(amount);
+        }
+
+        self.storage_balance_of(account_id).unwrap()
+    }
+
+    #[payable]
+    pub fn storage_unregister(&mut self, force: Option<bool>) -> bool {
+        assert_one_yocto();
+        let account_id = env::predecessor_account_id();
+
+        let storage_account = self.storage_accounts.get(&account_id).cloned();
+        if storage_account.is_none() {
+            log_str("Account is not registered");
+            return false;
+        }
+
+        // records owned by the account are never force-deleted
+        let storage_account = storage_account.unwrap();
+        if storage_account.used_bytes > 0 {
+            if force.unwrap_or(false) {
+                env::panic_str("Force unregistering is not supported")
+            }
+            env::panic_str("Cannot unregister an account that still owns stored data")
+        }
+
+        self.storage_accounts.remove(&account_id);
+        Promise::new(account_id.clone()).transfer(storage_account.total);
+
+        log_str(&format!("Storage account unregistered: {}", account_id));
+
+        true
+    }
+
+    pub fn storage_balance_bounds(&self) -> StorageBalanceBounds {
+        StorageBalanceBounds {
+            min: U128(REGISTRATION_STORAGE_BYTES as u128 * env::storage_byte_cost()),
+            max: None,
+        }
+    }
+
+    pub fn storage_balance_of(&self, account_id: AccountId) -> Option<StorageBalance> {
+        let min_balance = self.storage_balance_bounds().min.0;
+        self.storage_accounts
+            .get(&account_id)
+            .map(|storage_account| StorageBalance {
+                total: U128(storage_account.total),
+                available: U128(
+                    storage_account
+                        .total
+                        .saturating_sub(min_balance)
+                        .saturating_sub(
+                            storage_account.used_bytes as u128 * env::storage_byte_cost(),
+                        ),
+                ),
+            })
+    }
+}
+
+impl Contract {
+    /// Charges `account_id` for the storage added since `initial_storage` was
+    /// measured, or releases it when the call freed storage. Panics (reverting
+    /// the whole call) with `INSUFFICIENT_DEPOSIT` when the account's storage
+    /// balance does not cover it, and while a state migration is running.
+    pub(crate) fn charge_storage(&mut self, account_id: &AccountId, initial_storage: u64) {
+        self.assert_not_migrating();
+
+        // cached collection writes only hit storage on flush
+        let final_storage = self.storage_checkpoint();
+
+        let storage_account = self.storage_accounts.get(account_id).cloned();
+        if storage_account.is_none() {
+            if final_storage > initial_storage {
+                ContractError::InsufficientDeposit(
+                    "Account has no storage balance, call storage_deposit first".to_string(),
+                )
+                .panic()
+            }
+            return;
+        }
+
+        let mut storage_account = storage_account.unwrap();
+        if final_storage > initial_storage {
+            storage_account.used_bytes += final_storage - initial_storage;
+
+            let required = self.storage_balance_bounds().min.0
+                + storage_account.used_bytes as u128 * env::storage_byte_cost();
+            if storage_account.total < required {
+                ContractError::InsufficientDeposit(format!(
+                    "Insufficient storage balance: {} required, {} deposited",
+                    required, storage_account.total
+                ))
+                .panic()
+            }
+        } else {
+            let released = initial_storage - final_storage;
+            storage_account.used_bytes = storage_account.used_bytes.saturating_sub(released);
+        }
+
+        self.storage_accounts
+            .insert(account_id.clone(), storage_account);
+    }
+
+    /// Flushes the pending writes and returns the storage usage, the
+    /// baseline of `release_storage`.
+    pub(crate) fn storage_checkpoint(&mut self) -> u64 {
+        self.flush_collections();
+        env::storage_usage()
+    }
+
+    /// Books the storage change since `owner_storage`, a checkpoint taken
+    /// before changing records `owner_id` pays for, to that account: freed
+    /// bytes are credited and added ones are charged without a balance check,
+    /// so callbacks can use it without panicking. Returns `initial_storage`
+    /// moved by as many bytes so that `charge_storage` does not book them to
+    /// the caller too.
+    pub(crate) fn release_storage(
+        &mut self,
+        owner_id: &AccountId,
+        owner_storage: u64,
+        initial_storage: u64,
+    ) -> u64 {
+        let final_storage = self.storage_checkpoint();
+        let storage_account = self.storage_accounts.get_mut(owner_id);
+        if final_storage > owner_storage {
+            let added = final_storage - owner_storage;
+            if let Some(storage_account) = storage_account {
+                storage_account.used_bytes += added;
+            }
+            return initial_storage + added;
+        }
+
+        let released = owner_storage - final_storage;
+        if let Some(storage_account) = storage_account {
+            storage_account.used_bytes = storage_account.used_bytes.saturating_sub(released);
+        }
+        initial_storage.saturating_sub(released)
+    }
+
+    /// Writes every cached collection to storage. The destructuring lists
+    /// each field of the contract so a new collection does not compile until
+    /// it is flushed here.
+    pub(crate) fn flush_collections(&mut self) {
+        let Contract {
+            owner_id: _,
+            roles,
+            storage_accounts,
+            pending_upgrade: _,
+            migration,
+            fee_bps: _,
+            treasury_balance: _,
+            course_fees,
+            mentor_fees,
+            mentor_earnings,
+            payouts,
+            mentor_payouts,
+            payout_count: _,
+            accepted_tokens,
+            course_token_prices,
+            token_earnings,
+            token_treasury,
+            certificates,
+            enrollment_certificates,
+            student_certificates,
+            certificate_count: _,
+            revoked_certificate_count: _,
+            reviews,
+            course_reviews,
+            student_reviews,
+            review_count: _,
+            refund_policy: _,
+            enrollment_payments,
+            mentor_escrows,
+            course_escrow_policies,
+            users,
+            usernames,
+            emails,
+            courses,
+            mentor_courses,
+            modules,
+            lessons,
+            enrollments,
+            enrollment_ids,
+            student_enrollments,
+            course_enrollments,
+            quizzes,
+            module_progresses,
+            lesson_progresses,
+            quizz_progresses,
+            user_count: _,
+            course_count: _,
+            module_count: _,
+            lesson_count: _,
+            assignment_count: _,
+            enrollment_count: _,
+            module_progress_count: _,
+            lesson_progress_count: _,
+            quizz_progress_count: _,
+            quizz_count: _,
+            test_count: _,
+        } = self;
+
+        roles.flush();
+        storage_accounts.flush();
+        course_fees.flush();
+        mentor_fees.flush();
+        mentor_earnings.flush();
+        payouts.flush();
+        mentor_payouts.flush();
+        accepted_tokens.flush();
+        course_token_prices.flush();
+        token_earnings.flush();
+        token_treasury.flush();
+        certificates.flush();
+        enrollment_certificates.flush();
+        student_certificates.flush();
+        reviews.flush();
+        course_reviews.flush();
+        student_reviews.flush();
+        enrollment_payments.flush();
+        mentor_escrows.flush();
+        course_escrow_policies.flush();
+        users.flush();
+        usernames.flush();
+        emails.flush();
+        courses.flush();
+        mentor_courses.flush();
+        modules.flush();
+        lessons.flush();
+        enrollments.flush();
+        enrollment_ids.flush();
+        student_enrollments.flush();
+        course_enrollments.flush();
+        quizzes.flush();
+        module_progresses.flush();
+        lesson_progresses.flush();
+        quizz_progresses.flush();
+        if let Some(migration) = migration {
+            migration.flush();
+        }
+
+        write_state_version();
+    }
+}
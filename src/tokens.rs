@@ -0,0 +1,331 @@
+use crate::earnings::GAS_FOR_FT_TRANSFER;
+use crate::errors::ContractError;
+use crate::events::*;
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::serde_json::{self, json};
+use near_sdk::{
+    env, is_promise_success, near_bindgen, AccountId, FunctionError, Gas, Promise, PromiseOrValue,
+};
+
+/// Gas attached to the callback that settles a token treasury withdrawal.
+const GAS_FOR_RESOLVE_WITHDRAW: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    #[handle_result]
+    pub fn add_accepted_token(&mut self, token_id: AccountId) -> Result<bool, ContractError> {
+        let owner_id = self.require_role(Role::Owner, "Only the owner can accept new tokens")?;
+        let initial_storage = env::storage_usage();
+
+        if !self.accepted_tokens.insert(token_id.clone()) {
+            return Err(ContractError::AlreadyExists(format!(
+                "Token {} is already accepted",
+                token_id
+            )));
+        }
+
+        log_str(&format!("Token accepted: {}", token_id));
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        Ok(true)
+    }
+
+    /// Stops accepting a token for new payments. Balances already held in it
+    /// stay withdrawable.
+    #[handle_result]
+    pub fn remove_accepted_token(&mut self, token_id: AccountId) -> Result<bool, ContractError> {
+        let owner_id = self.require_role(Role::Owner, "Only the owner can remove tokens")?;
+        let initial_storage = env::storage_usage();
+
+        if !self.accepted_tokens.remove(&token_id) {
+            return Err(ContractError::NotFound(format!(
+                "Token {} is not accepted",
+                token_id
+            )));
+        }
+
+        log_str(&format!("Token removed: {}", token_id));
+
+        // release the storage freed by the caller
+        self.charge_storage(&owner_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn get_accepted_tokens(&self) -> Vec<AccountId> {
+        self.accepted_tokens.iter().cloned().collect()
+    }
+
+    /// Sets the price of a course in `token_id`, the platform fee is added on
+    /// top of it like for NEAR prices.
+    #[handle_result]
+    pub fn set_course_token_price(
+        &mut self,
+        course_id: u64,
+        token_id: AccountId,
+        price: U128,
+    ) -> Result<bool, ContractError> {
+        let initial_storage = env::storage_usage();
+
+        // check if the course exists
+        let course = self.get_course_by_id(course_id);
+        if course.is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+
+        // only the mentor of the course and admins can price it
+        let account_id = self.require_course_mentor(
+            course.as_ref().unwrap(),
+            "Only the mentor of the course can set its token prices",
+        )?;
+
+        // check if the token is accepted
+        if !self.accepted_tokens.contains(&token_id) {
+            return Err(ContractError::InvalidInput(format!(
+                "Token {} is not accepted",
+                token_id
+            )));
+        }
+
+        self.course_token_prices
+            .insert((course_id, token_id.clone()), price.0);
+
+        log_str(&format!(
+            "Course {} priced at {} {}",
+            course_id, price.0, token_id
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
+    }
+
+    #[handle_result]
+    pub fn remove_course_token_price(
+        &mut self,
+        course_id: u64,
+        token_id: AccountId,
+    ) -> Result<bool, ContractError> {
+        let initial_storage = env::storage_usage();
+
+        // check if the course exists
+        let course = self.get_course_by_id(course_id);
+        if course.is_none() {
+            return Err(ContractError::NotFound("Course does not exist".to_string()));
+        }
+
+        // only the mentor of the course and admins can price it
+        let account_id = self.require_course_mentor(
+            course.as_ref().unwrap(),
+            "Only the mentor of the course can remove its token prices",
+        )?;
+
+        if self
+            .course_token_prices
+            .remove(&(course_id, token_id.clone()))
+            .is_none()
+        {
+            return Err(ContractError::NotFound(format!(
+                "Course {} has no price in {}",
+                course_id, token_id
+            )));
+        }
+
+        // release the storage freed by the caller
+        self.charge_storage(&account_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn get_course_token_prices(&self, course_id: u64) -> Vec<CourseTokenPrice> {
+        let mut prices: Vec<CourseTokenPrice> = vec![];
+        for token_id in self.accepted_tokens.iter() {
+            if let Some(price) = self.course_token_prices.get(&(course_id, token_id.clone())) {
+                prices.push(CourseTokenPrice {
+                    token_id: token_id.clone(),
+                    price: U128(*price),
+                });
+            }
+        }
+        prices
+    }
+
+    /// NEP-141 receiver. `msg` is `{"course_ids":[...]}`; every listed course
+    /// the sender has carted and that is priced in the sent token gets
+    /// enrolled. Tokens not spent on those courses are returned.
+    pub fn ft_on_transfer(
+        &mut self,
+        sender_id: AccountId,
+        amount: U128,
+        msg: String,
+    ) -> PromiseOrValue<U128> {
+        let token_id: AccountId = env::predecessor_account_id();
+        let initial_storage = env::storage_usage();
+
+        // check if the token is accepted
+        if !self.accepted_tokens.contains(&token_id) {
+            ContractError::InvalidInput(format!("Token {} is not accepted", token_id)).panic()
+        }
+
+        let enroll_msg = serde_json::from_str::<FtEnrollMsg>(&msg);
+        if enroll_msg.is_err() {
+            ContractError::InvalidInput("Invalid msg, expected {\"course_ids\":[...]}".to_string())
+                .panic()
+        }
+        let enroll_msg = enroll_msg.unwrap();
+
+        let amount: u128 = amount.into();
+        let enrolled_at = env::block_timestamp();
+        let mut total_courses_price: u128 = 0;
+
+        for course_id in enroll_msg.course_ids.iter() {
+            // only carted courses can be paid for
+            let enrollment = self.get_enrollment(*course_id, sender_id.clone());
+            if enrollment.is_none()
+                || enrollment.as_ref().unwrap().status != EnrollmentStatus::Carted
+            {
+                log_str(&format!("Course {} is not carted, skipping", course_id));
+                continue;
+            }
+
+            // check if the course is priced in this token
+            let price = self
+                .course_token_prices
+                .get(&(*course_id, token_id.clone()))
+                .copied();
+            if price.is_none() {
+                log_str(&format!(
+                    "Course {} has no price in {}, skipping",
+                    course_id, token_id
+                ));
+                continue;
+            }
+            let price = price.unwrap();
+            let course_fee = self.calculate_course_fee(price);
+
+            total_courses_price += price + course_fee;
+            if total_courses_price > amount {
+                ContractError::InsufficientDeposit(
+                    "Transferred amount is less than the total courses price".to_string(),
+                )
+                .panic()
+            }
+
+            // enroll the student and create their progress rows
+            let course = self
+                .internal_enroll(enrollment.as_ref().unwrap(), enrolled_at)
+                .unwrap_or_else(|error| error.panic());
+
+            // escrow the mentor's share and book the platform fee in this token
+            let enrollment = enrollment.unwrap();
+            self.internal_record_payment(
+                &enrollment,
+                &course,
+                Some(token_id.clone()),
+                price,
+                course_fee,
+            );
+
+            // emit the enrolled event
+            CourchainEvent::Enrolled(vec![EnrolledData {
+                enrollment_id: enrollment.id,
+                course_id: course.id,
+                student_id: sender_id.clone(),
+                mentor_id: course.mentor_id.clone(),
+                token_id: Some(token_id.clone()),
+                price: U128(price),
+                fee: U128(course_fee),
+                enrolled_at,
+            }])
+            .emit();
+        }
+
+        // charge the student for the storage used
+        self.charge_storage(&sender_id, initial_storage);
+
+        // the token contract refunds whatever is returned here
+        let unused = amount - total_courses_price;
+        if unused > 0 {
+            log_str(&format!(
+                "Returning {} {} to {}",
+                unused, token_id, sender_id
+            ));
+        }
+
+        PromiseOrValue::Value(U128(unused))
+    }
+
+    pub fn get_token_treasury_balance(&self, token_id: AccountId) -> U128 {
+        U128(self.token_treasury.get(&token_id).copied().unwrap_or(0))
+    }
+
+    pub fn withdraw_token_treasury(
+        &mut self,
+        token_id: AccountId,
+        amount: U128,
+        receiver: AccountId,
+    ) -> Promise {
+        let owner_id = self.assert_role(Role::Owner, "Only the owner can withdraw the treasury");
+        let initial_storage = env::storage_usage();
+
+        let amount: u128 = amount.into();
+        let balance = self.token_treasury.get(&token_id).copied().unwrap_or(0);
+        if amount == 0 {
+            ContractError::InvalidInput("The amount must be greater than zero".to_string()).panic()
+        }
+        if amount > balance {
+            ContractError::InvalidInput(format!(
+                "Cannot withdraw {} {}, the treasury holds {}",
+                amount, token_id, balance
+            ))
+            .panic()
+        }
+
+        // debit first, the callback credits it back if the transfer fails
+        self.token_treasury
+            .insert(token_id.clone(), balance - amount);
+
+        log_str(&format!(
+            "Withdrawing {} {} from the treasury to {}",
+            amount, token_id, receiver
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        Promise::new(token_id.clone())
+            .function_call(
+                "ft_transfer".to_string(),
+                json!({ "receiver_id": receiver, "amount": U128(amount) })
+                    .to_string()
+                    .into_bytes(),
+                1,
+                GAS_FOR_FT_TRANSFER,
+            )
+            .then(
+                Self::ext(env::current_account_id())
+                    .with_static_gas(GAS_FOR_RESOLVE_WITHDRAW)
+                    .on_token_treasury_withdrawn(token_id, U128(amount)),
+            )
+    }
+
+    #[private]
+    pub fn on_token_treasury_withdrawn(&mut self, token_id: AccountId, amount: U128) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+
+        *self.token_treasury.entry(token_id.clone()).or_insert(0) += amount.0;
+        log_str(&format!(
+            "Treasury transfer of {} failed, {} returned to the treasury",
+            token_id, amount.0
+        ));
+        false
+    }
+}
@@ -0,0 +1,133 @@
+use crate::errors::ContractError;
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::U128;
+use near_sdk::{env, is_promise_success, near_bindgen, AccountId, FunctionError, Gas, Promise};
+
+/// Platform fee applied until the owner configures one: 10%.
+pub const DEFAULT_FEE_BPS: u16 = 1_000;
+
+/// Basis points in 100%, the upper bound of `fee_bps`.
+pub const MAX_FEE_BPS: u16 = 10_000;
+
+/// Gas attached to the callback that settles a treasury withdrawal.
+const GAS_FOR_RESOLVE_WITHDRAW: Gas = Gas(10_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    #[handle_result]
+    pub fn set_fee_bps(&mut self, fee_bps: u16) -> Result<bool, ContractError> {
+        let owner_id =
+            self.require_role(Role::Owner, "Only the owner can change the platform fee")?;
+        let initial_storage = env::storage_usage();
+
+        if fee_bps > MAX_FEE_BPS {
+            return Err(ContractError::InvalidInput(format!(
+                "The fee cannot exceed {} basis points",
+                MAX_FEE_BPS
+            )));
+        }
+
+        log_str(&format!(
+            "Platform fee changed from {} to {} basis points",
+            self.fee_bps, fee_bps
+        ));
+        self.fee_bps = fee_bps;
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        Ok(true)
+    }
+
+    pub fn get_fee_bps(&self) -> u16 {
+        self.fee_bps
+    }
+
+    pub fn get_treasury_balance(&self) -> U128 {
+        U128(self.treasury_balance)
+    }
+
+    pub fn get_course_accrued_fees(&self, course_id: u64) -> U128 {
+        U128(self.course_fees.get(&course_id).copied().unwrap_or(0))
+    }
+
+    pub fn get_mentor_accrued_fees(&self, mentor_id: AccountId) -> U128 {
+        U128(self.mentor_fees.get(&mentor_id).copied().unwrap_or(0))
+    }
+
+    pub fn withdraw_treasury(&mut self, amount: U128, receiver: AccountId) -> Promise {
+        let owner_id = self.assert_role(Role::Owner, "Only the owner can withdraw the treasury");
+        let initial_storage = env::storage_usage();
+
+        let amount: u128 = amount.into();
+        if amount == 0 {
+            ContractError::InvalidInput("The amount must be greater than zero".to_string()).panic()
+        }
+        if amount > self.treasury_balance {
+            ContractError::InvalidInput(format!(
+                "Cannot withdraw {}, the treasury holds {}",
+                amount, self.treasury_balance
+            ))
+            .panic()
+        }
+
+        // debit first, the callback credits it back if the transfer fails
+        self.treasury_balance -= amount;
+
+        log_str(&format!(
+            "Withdrawing {} from the treasury to {}",
+            amount, receiver
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        Promise::new(receiver).transfer(amount).then(
+            Self::ext(env::current_account_id())
+                .with_static_gas(GAS_FOR_RESOLVE_WITHDRAW)
+                .on_treasury_withdrawn(U128(amount)),
+        )
+    }
+
+    #[private]
+    pub fn on_treasury_withdrawn(&mut self, amount: U128) -> bool {
+        if is_promise_success() {
+            return true;
+        }
+
+        self.treasury_balance += amount.0;
+        log_str(&format!(
+            "Treasury transfer failed, {} returned to the treasury",
+            amount.0
+        ));
+        false
+    }
+}
+
+impl Contract {
+    /// Platform fee charged on top of the course price.
+    pub(crate) fn calculate_course_fee(&self, course_price: u128) -> u128 {
+        course_price * self.fee_bps as u128 / MAX_FEE_BPS as u128
+    }
+
+    /// Books the fee of a payment into the treasury of the token it was paid
+    /// in. NEAR fees are also totalled per course and per mentor. Called once
+    /// the payment can no longer be refunded, so the treasury never holds a
+    /// fee that may still have to be paid back.
+    pub(crate) fn internal_credit_fee(&mut self, payment: &EnrollmentPayment) {
+        let fee = payment.fee.0;
+        if let Some(token_id) = &payment.token_id {
+            *self.token_treasury.entry(token_id.clone()).or_insert(0) += fee;
+            return;
+        }
+
+        self.treasury_balance += fee;
+        *self.course_fees.entry(payment.course_id).or_insert(0) += fee;
+        *self
+            .mentor_fees
+            .entry(payment.mentor_id.clone())
+            .or_insert(0) += fee;
+    }
+}
@@ -0,0 +1,1300 @@
+use crate::errors::ContractError;
+use crate::migrations::*;
+use crate::models::*;
+use crate::Contract;
+use near_sdk::json_types::U128;
+use near_sdk::store::Vector;
+use near_sdk::test_utils::VMContextBuilder;
+use near_sdk::{
+    env, testing_env, AccountId, PromiseOrValue, PromiseResult, RuntimeFeesConfig, VMConfig,
+    ONE_NEAR,
+};
+
+const DAY: u64 = 24 * 60 * 60 * 1_000_000_000;
+const START: u64 = 1_700_000_000_000_000_000;
+const PRICE: u128 = 6 * ONE_NEAR;
+const FEE: u128 = PRICE / 10;
+
+fn owner() -> AccountId {
+    "courchain.near".parse().unwrap()
+}
+
+fn mentor() -> AccountId {
+    "mentor.near".parse().unwrap()
+}
+
+fn student() -> AccountId {
+    "student.near".parse().unwrap()
+}
+
+fn other_student() -> AccountId {
+    "other_student.near".parse().unwrap()
+}
+
+/// Signs the next calls as `signer` at `timestamp`, attaching `deposit`.
+fn set_context(signer: &AccountId, deposit: u128, timestamp: u64) {
+    testing_env!(VMContextBuilder::new()
+        .current_account_id(owner())
+        .signer_account_id(signer.clone())
+        .predecessor_account_id(signer.clone())
+        .attached_deposit(deposit)
+        .block_timestamp(timestamp)
+        .build());
+}
+
+/// Runs the next callback with the result of the promise it waits on.
+fn set_callback_context(timestamp: u64, result: PromiseResult) {
+    testing_env!(
+        VMContextBuilder::new()
+            .current_account_id(owner())
+            .signer_account_id(owner())
+            .predecessor_account_id(owner())
+            .block_timestamp(timestamp)
+            .build(),
+        VMConfig::test(),
+        RuntimeFeesConfig::test(),
+        Default::default(),
+        vec![result],
+    );
+}
+
+fn register(contract: &mut Contract, account_id: &AccountId) {
+    set_context(account_id, 10 * ONE_NEAR, START);
+    contract.storage_deposit(None, None);
+
+    set_context(account_id, 0, START);
+    assert!(contract
+        .create_user(
+            account_id.to_string(),
+            account_id.to_string(),
+            "0600000000".to_string(),
+            format!("{}@mail.com", account_id),
+            false,
+            "".to_string(),
+            None,
+        )
+        .unwrap());
+}
+
+/// A contract with a published one lesson course of `mentor`.
+fn setup() -> Contract {
+    set_context(&owner(), 0, START);
+    let mut contract = Contract::default();
+
+    register(&mut contract, &owner());
+    register(&mut contract, &mentor());
+    register(&mut contract, &student());
+    register(&mut contract, &other_student());
+
+    set_context(&owner(), 0, START);
+    assert!(contract.grant_role(mentor(), Role::Mentor).unwrap());
+
+    set_context(&mentor(), 0, START);
+    contract
+        .create_course(
+            "Rust".to_string(),
+            "Rust course".to_string(),
+            "beginner".to_string(),
+            "1 month".to_string(),
+            "programming".to_string(),
+            vec![],
+            vec![],
+            "".to_string(),
+            false,
+            U128(PRICE),
+            None,
+        )
+        .unwrap();
+    contract
+        .create_module(
+            0,
+            "Basics".to_string(),
+            "".to_string(),
+            ModuleStatus::Draft,
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+    contract
+        .create_lesson(
+            0,
+            "Ownership".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            1,
+            false,
+            None,
+        )
+        .unwrap();
+    contract.publish_course(0).unwrap();
+
+    contract
+}
+
+fn enroll(contract: &mut Contract, account_id: &AccountId, timestamp: u64) -> u64 {
+    set_context(account_id, 0, timestamp);
+    contract.save_course_to_cart(0).unwrap();
+
+    set_context(account_id, PRICE + FEE, timestamp);
+    let receipt = contract.enroll_all_carted_courses().unwrap();
+    receipt.courses[0].enrollment_id
+}
+
+fn enrollment_status(contract: &Contract, enrollment_id: u64) -> EnrollmentStatus {
+    contract.enrollments.get(&enrollment_id).unwrap().status
+}
+
+fn payment_status(contract: &Contract, enrollment_id: u64) -> PaymentStatus {
+    contract
+        .get_enrollment_payment(enrollment_id)
+        .unwrap()
+        .status
+}
+
+#[test]
+fn refund_within_the_window() {
+    let mut contract = setup();
+    let enrollment_id = enroll(&mut contract, &student(), START);
+
+    set_context(&student(), 0, START + DAY);
+    assert!(contract.request_refund(0).unwrap());
+    assert_eq!(
+        enrollment_status(&contract, enrollment_id),
+        EnrollmentStatus::Refunding
+    );
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Refunding
+    );
+    assert!(contract
+        .get_mentor_escrowed_payments(mentor(), None, None)
+        .is_empty());
+
+    set_callback_context(START + DAY, PromiseResult::Successful(vec![]));
+    assert!(contract.on_refund_resolved(enrollment_id));
+    assert_eq!(
+        enrollment_status(&contract, enrollment_id),
+        EnrollmentStatus::Refunded
+    );
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Refunded
+    );
+    assert_eq!(contract.get_treasury_balance(), U128(0));
+}
+
+#[test]
+fn refund_outside_the_window() {
+    let mut contract = setup();
+    let enrollment_id = enroll(&mut contract, &student(), START);
+
+    set_context(&student(), 0, START + 8 * DAY);
+    assert!(!contract.is_refundable(0, student()));
+    assert!(matches!(
+        contract.request_refund(0),
+        Err(ContractError::InvalidState(_))
+    ));
+    assert_eq!(
+        enrollment_status(&contract, enrollment_id),
+        EnrollmentStatus::Enrolled
+    );
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Escrowed
+    );
+}
+
+#[test]
+fn failed_refund_transfer_restores_the_enrollment() {
+    let mut contract = setup();
+    let enrollment_id = enroll(&mut contract, &student(), START);
+    let student_bytes = used_bytes(&contract, &student());
+
+    set_context(&student(), 0, START + DAY);
+    assert!(contract.request_refund(0).unwrap());
+    assert!(used_bytes(&contract, &student()) < student_bytes);
+
+    set_callback_context(START + DAY, PromiseResult::Failed);
+    assert!(!contract.on_refund_resolved(enrollment_id));
+    assert_eq!(
+        enrollment_status(&contract, enrollment_id),
+        EnrollmentStatus::Enrolled
+    );
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Escrowed
+    );
+    assert_eq!(
+        contract
+            .get_mentor_escrowed_payments(mentor(), None, None)
+            .len(),
+        1
+    );
+    // and pays for the restored records again
+    assert_eq!(used_bytes(&contract, &student()), student_bytes);
+
+    // the student can ask again
+    set_context(&student(), 0, START + DAY);
+    assert!(contract.is_refundable(0, student()));
+}
+
+#[test]
+fn refund_after_a_treasury_withdrawal() {
+    let mut contract = setup();
+    let first_enrollment_id = enroll(&mut contract, &student(), START);
+
+    // the fee is held until the refund window closes
+    assert_eq!(contract.get_treasury_balance(), U128(0));
+
+    // anyone can settle the mentor's sales
+    set_context(&other_student(), 0, START + 8 * DAY);
+    assert_eq!(contract.release_escrow(mentor(), None, None), 1);
+    assert_eq!(
+        payment_status(&contract, first_enrollment_id),
+        PaymentStatus::Released
+    );
+    assert_eq!(contract.get_treasury_balance(), U128(FEE));
+
+    // the owner withdraws the whole treasury
+    set_context(&owner(), 0, START + 8 * DAY);
+    contract.withdraw_treasury(U128(FEE), owner());
+    set_callback_context(START + 8 * DAY, PromiseResult::Successful(vec![]));
+    assert!(contract.on_treasury_withdrawn(U128(FEE)));
+    assert_eq!(contract.get_treasury_balance(), U128(0));
+
+    // a later refund pays its own held fee back, not the treasury's
+    let second_enrollment_id = enroll(&mut contract, &other_student(), START + 9 * DAY);
+    set_context(&other_student(), 0, START + 10 * DAY);
+    assert!(contract.request_refund(0).unwrap());
+    set_callback_context(START + 10 * DAY, PromiseResult::Successful(vec![]));
+    assert!(contract.on_refund_resolved(second_enrollment_id));
+
+    assert_eq!(
+        payment_status(&contract, second_enrollment_id),
+        PaymentStatus::Refunded
+    );
+    assert_eq!(contract.get_treasury_balance(), U128(0));
+}
+
+#[test]
+fn migrate_from_v0() {
+    set_context(&owner(), 0, START);
+
+    let mut old = ContractV0 {
+        owner_id: owner(),
+        users: Vector::new(b"u".to_vec()),
+        courses: Vector::new(b"c".to_vec()),
+        modules: Vector::new(b"m".to_vec()),
+        lessons: Vector::new(b"l".to_vec()),
+        enrollments: Vector::new(b"e".to_vec()),
+        quizzes: Vector::new(b"q".to_vec()),
+        module_progresses: Vector::new(b"mp".to_vec()),
+        lesson_progresses: Vector::new(b"lp".to_vec()),
+        quizz_progresses: Vector::new(b"qp".to_vec()),
+        user_count: 2,
+        course_count: 1,
+        module_count: 0,
+        lesson_count: 0,
+        assignment_count: 0,
+        enrollment_count: 1,
+        module_progress_count: 0,
+        lesson_progress_count: 0,
+        quizz_progress_count: 0,
+        quizz_count: 0,
+        test_count: 0,
+    };
+    for (account_id, role) in [(mentor(), "admin"), (student(), "student")] {
+        old.users.push(UserV0 {
+            account_id: account_id.clone(),
+            name: account_id.to_string(),
+            username: account_id.to_string(),
+            email: format!("{}@mail.com", account_id),
+            phone: "".to_string(),
+            role: role.to_string(),
+            by_google: false,
+            certifications: None,
+            picture: "".to_string(),
+            created_at: 1_700_000_000_000,
+            updated_at: 1_700_000_000_000,
+        });
+    }
+    old.courses.push(CourseV0 {
+        id: 0,
+        title: "Rust".to_string(),
+        description: "".to_string(),
+        level: "beginner".to_string(),
+        duration: "".to_string(),
+        status: "published".to_string(),
+        requirements: vec![],
+        objectives: vec![],
+        category: "".to_string(),
+        picture: "".to_string(),
+        with_ai: false,
+        price: PRICE,
+        mentor_id: mentor(),
+        modules_ids: vec![],
+        created_at: 1_700_000_000_000,
+        updated_at: 1_700_000_000_000,
+    });
+    old.enrollments.push(EnrollmentV0 {
+        id: 0,
+        course_id: 0,
+        student_id: student(),
+        status: "enrolled".to_string(),
+        progress: 0,
+        carted_at: 1_700_000_000_000,
+        enrolled_at: Some(1_700_000_000_000),
+        completed_at: None,
+        course_review: None,
+        updated_at: 1_700_000_000_000,
+    });
+    old.users.flush();
+    old.courses.flush();
+    old.enrollments.flush();
+    env::state_write(&old);
+
+    let mut contract = Contract::migrate();
+    assert_eq!(contract.user_count, 2);
+    assert!(contract.get_user_by_id(mentor()).is_none());
+
+    // writes wait for the records to be moved
+    set_context(&owner(), 0, START);
+    let mut batches = 0;
+    while !contract.migrate_state_batch(2) {
+        batches += 1;
+        assert!(contract.get_migration_cursor().is_some());
+    }
+    assert!(batches > 1);
+    assert!(contract.get_migration_cursor().is_none());
+
+    let user = contract.get_user_by_id(mentor()).unwrap();
+    assert_eq!(user.role, UserRole::Admin);
+    assert_eq!(user.created_at, START);
+    assert!(contract.has_role(mentor(), Role::Admin));
+    assert!(!contract.has_role(student(), Role::Admin));
+
+    let course = contract.get_course_by_id(0).unwrap();
+    assert_eq!(course.status, CourseStatus::Published);
+    assert_eq!(course.mentor_id, mentor());
+
+    let enrollment = contract.get_enrollment(0, student()).unwrap();
+    assert_eq!(enrollment.status, EnrollmentStatus::Enrolled);
+    assert_eq!(enrollment.enrolled_at, Some(START));
+
+    // the migrated state is stamped and loads as current
+    env::state_write(&contract);
+    assert!(matches!(
+        VersionedContract::load(),
+        VersionedContract::Current(_)
+    ));
+}
+
+fn create_module(contract: &mut Contract, course_id: u64) -> Result<bool, ContractError> {
+    contract.create_module(
+        course_id,
+        "Advanced".to_string(),
+        "".to_string(),
+        ModuleStatus::Draft,
+        2,
+        false,
+        None,
+    )
+}
+
+#[test]
+fn grant_and_revoke_roles() {
+    let mut contract = setup();
+
+    // admins are granted by the owner only
+    set_context(&mentor(), 0, START);
+    assert!(matches!(
+        contract.grant_role(student(), Role::Admin),
+        Err(ContractError::Unauthorized(_))
+    ));
+    set_context(&owner(), 0, START);
+    assert!(contract.grant_role(student(), Role::Admin).unwrap());
+    assert!(matches!(
+        contract.grant_role(student(), Role::Admin),
+        Err(ContractError::AlreadyExists(_))
+    ));
+    assert!(contract.has_role(student(), Role::Moderator));
+    assert_eq!(
+        contract.get_role_members(Role::Admin, None, None),
+        vec![student()]
+    );
+
+    // admins manage the moderators
+    set_context(&student(), 0, START);
+    assert!(contract
+        .grant_role(other_student(), Role::Moderator)
+        .unwrap());
+    assert!(contract
+        .revoke_role(other_student(), Role::Moderator)
+        .unwrap());
+    assert!(matches!(
+        contract.revoke_role(other_student(), Role::Moderator),
+        Err(ContractError::NotFound(_))
+    ));
+    assert!(contract.get_account_roles(other_student()).is_empty());
+
+    set_context(&owner(), 0, START);
+    assert!(matches!(
+        contract.revoke_role(owner(), Role::Owner),
+        Err(ContractError::InvalidInput(_))
+    ));
+    assert!(contract.revoke_role(student(), Role::Admin).unwrap());
+    assert!(!contract.has_role(student(), Role::Admin));
+}
+
+#[test]
+fn course_content_needs_the_course_mentor() {
+    let mut contract = setup();
+
+    // courses are created by mentors only
+    set_context(&student(), 0, START);
+    assert!(matches!(
+        contract.create_course(
+            "Go".to_string(),
+            "Go course".to_string(),
+            "beginner".to_string(),
+            "1 month".to_string(),
+            "programming".to_string(),
+            vec![],
+            vec![],
+            "".to_string(),
+            false,
+            U128(PRICE),
+            None,
+        ),
+        Err(ContractError::Unauthorized(_))
+    ));
+
+    // another mentor cannot change the course
+    set_context(&owner(), 0, START);
+    assert!(contract.grant_role(student(), Role::Mentor).unwrap());
+    set_context(&student(), 0, START);
+    assert!(matches!(
+        create_module(&mut contract, 0),
+        Err(ContractError::Unauthorized(_))
+    ));
+
+    // the course mentor loses access with the role, an admin keeps it
+    set_context(&owner(), 0, START);
+    assert!(contract.revoke_role(mentor(), Role::Mentor).unwrap());
+    set_context(&mentor(), 0, START);
+    assert!(matches!(
+        create_module(&mut contract, 0),
+        Err(ContractError::Unauthorized(_))
+    ));
+    set_context(&owner(), 0, START);
+    assert!(contract.grant_role(other_student(), Role::Admin).unwrap());
+    set_context(&other_student(), 0, START);
+    assert!(create_module(&mut contract, 0).unwrap());
+}
+
+fn answer(text: &str, is_correct: bool) -> Answer {
+    Answer {
+        text: text.to_string(),
+        is_correct,
+    }
+}
+
+/// Adds a two questions quizz to module 0, the first answer being correct.
+fn add_quizz(contract: &mut Contract) -> u64 {
+    set_context(&mentor(), 0, START);
+    assert!(contract
+        .create_quizz(0, "Borrowing".to_string(), "".to_string(), None)
+        .unwrap());
+    let questions = ["Who owns a value?", "How many mutable borrows?"]
+        .iter()
+        .map(|text| Question {
+            text: text.to_string(),
+            answers: vec![answer("right", true), answer("wrong", false)],
+        })
+        .collect();
+    contract.save_quizz_questions(0, false, questions).unwrap();
+    0
+}
+
+#[test]
+fn submit_quizz_grades_every_question() {
+    let mut contract = setup();
+    let quizz_id = add_quizz(&mut contract);
+
+    // students only get the answers without their correctness
+    assert!(matches!(
+        contract.get_quizz_with_answers(quizz_id, student()),
+        Err(ContractError::Unauthorized(_))
+    ));
+    let quizz = contract.get_quizz_with_answers(quizz_id, mentor()).unwrap();
+    assert!(quizz.questions[0].answers[0].is_correct);
+
+    // only enrolled students can submit
+    set_context(&student(), 0, START);
+    assert!(matches!(
+        contract.submit_quizz(quizz_id, vec![0, 0]),
+        Err(ContractError::InvalidState(_))
+    ));
+    enroll(&mut contract, &student(), START);
+
+    set_context(&student(), 0, START);
+    assert!(matches!(
+        contract.submit_quizz(quizz_id, vec![0]),
+        Err(ContractError::InvalidInput(_))
+    ));
+    assert!(matches!(
+        contract.submit_quizz(quizz_id, vec![0, 2]),
+        Err(ContractError::InvalidInput(_))
+    ));
+
+    let result = contract.submit_quizz(quizz_id, vec![0, 1]).unwrap();
+    assert!(!result.passed);
+    assert_eq!(result.correct_count, 1);
+    assert_eq!(result.total_questions, 2);
+    assert!(!result.questions[1].is_correct);
+    assert_eq!(result.try_count, 1);
+
+    let result = contract.submit_quizz(quizz_id, vec![0, 0]).unwrap();
+    assert!(result.passed);
+    assert_eq!(result.try_count, 2);
+
+    // a passed quizz is not graded again
+    assert!(matches!(
+        contract.submit_quizz(quizz_id, vec![0, 0]),
+        Err(ContractError::InvalidState(_))
+    ));
+}
+
+fn used_bytes(contract: &Contract, account_id: &AccountId) -> u64 {
+    contract
+        .storage_accounts
+        .get(account_id)
+        .unwrap()
+        .used_bytes
+}
+
+#[test]
+fn storage_is_charged_to_the_caller() {
+    set_context(&owner(), 0, START);
+    let mut contract = Contract::default();
+
+    set_context(&student(), ONE_NEAR, START);
+    contract.storage_deposit(None, None);
+    assert_eq!(used_bytes(&contract, &student()), 0);
+
+    // the profile is billed to the student, out of their available balance
+    let initial_storage = env::storage_usage();
+    set_context(&student(), 0, START);
+    assert!(contract
+        .create_user(
+            "student".to_string(),
+            "student".to_string(),
+            "0600000000".to_string(),
+            "student@mail.com".to_string(),
+            false,
+            "".to_string(),
+            None,
+        )
+        .unwrap());
+    let charged = used_bytes(&contract, &student());
+    assert_eq!(charged, env::storage_usage() - initial_storage);
+
+    let min_balance = contract.storage_balance_bounds().min.0;
+    let balance = contract.storage_balance_of(student()).unwrap();
+    assert_eq!(
+        balance.available.0,
+        ONE_NEAR - min_balance - charged as u128 * env::storage_byte_cost()
+    );
+
+    // the used bytes are not withdrawable
+    set_context(&student(), 1, START);
+    let balance = contract.storage_withdraw(None);
+    assert_eq!(
+        balance.total.0,
+        min_balance + charged as u128 * env::storage_byte_cost()
+    );
+}
+
+#[test]
+fn freed_storage_goes_back_to_its_payer() {
+    let mut contract = setup();
+    // carting charges the student
+    let student_bytes = used_bytes(&contract, &student());
+    set_context(&student(), 0, START);
+    contract.save_course_to_cart(0).unwrap();
+    assert!(used_bytes(&contract, &student()) > student_bytes);
+
+    // deleting the course gives the student the bytes of their cart back
+    // and the mentor the bytes of the course
+    let mentor_bytes = used_bytes(&contract, &mentor());
+    set_context(&mentor(), 0, START);
+    contract.delete_course(0).unwrap();
+    assert_eq!(used_bytes(&contract, &student()), student_bytes);
+    assert!(used_bytes(&contract, &mentor()) < mentor_bytes);
+}
+
+#[test]
+fn fee_is_split_from_the_mentor_share() {
+    let mut contract = setup();
+
+    // only the owner sets the fee, up to 100%
+    set_context(&mentor(), 0, START);
+    assert!(matches!(
+        contract.set_fee_bps(500),
+        Err(ContractError::Unauthorized(_))
+    ));
+    set_context(&owner(), 0, START);
+    assert!(matches!(
+        contract.set_fee_bps(10_001),
+        Err(ContractError::InvalidInput(_))
+    ));
+    assert!(contract.set_fee_bps(500).unwrap());
+
+    // the student pays the fee on top of the price, the overpayment is
+    // given back
+    let fee = PRICE / 20;
+    set_context(&student(), 0, START);
+    contract.save_course_to_cart(0).unwrap();
+    set_context(&student(), PRICE + FEE, START);
+    let receipt = contract.enroll_all_carted_courses().unwrap();
+    let enrollment_id = receipt.courses[0].enrollment_id;
+
+    let payment = contract.get_enrollment_payment(enrollment_id).unwrap();
+    assert_eq!(payment.price, U128(PRICE));
+    assert_eq!(payment.fee, U128(fee));
+    assert_eq!(contract.get_mentor_earnings(mentor()).lifetime, U128(PRICE));
+
+    // the fee reaches the treasury once the sale is settled
+    assert_eq!(contract.get_treasury_balance(), U128(0));
+    set_context(&mentor(), 0, START + 8 * DAY);
+    contract.release_escrow(mentor(), None, None);
+    assert_eq!(contract.get_treasury_balance(), U128(fee));
+    assert_eq!(contract.get_course_accrued_fees(0), U128(fee));
+    assert_eq!(contract.get_mentor_accrued_fees(mentor()), U128(fee));
+    assert_eq!(contract.get_mentor_earnings(mentor()).pending, U128(PRICE));
+}
+
+#[test]
+fn failed_treasury_withdrawal_is_credited_back() {
+    let mut contract = setup();
+    enroll(&mut contract, &student(), START);
+    set_context(&mentor(), 0, START + 8 * DAY);
+    contract.release_escrow(mentor(), None, None);
+
+    set_context(&owner(), 0, START + 8 * DAY);
+    contract.withdraw_treasury(U128(FEE / 2), owner());
+    assert_eq!(contract.get_treasury_balance(), U128(FEE - FEE / 2));
+
+    set_callback_context(START + 8 * DAY, PromiseResult::Failed);
+    assert!(!contract.on_treasury_withdrawn(U128(FEE / 2)));
+    assert_eq!(contract.get_treasury_balance(), U128(FEE));
+}
+
+fn token() -> AccountId {
+    "usdc.near".parse().unwrap()
+}
+
+fn unused_tokens(result: PromiseOrValue<U128>) -> u128 {
+    match result {
+        PromiseOrValue::Value(unused) => unused.0,
+        PromiseOrValue::Promise(_) => panic!("expected the unused amount"),
+    }
+}
+
+#[test]
+fn ft_on_transfer_enrolls_and_returns_the_overpayment() {
+    let mut contract = setup();
+    let price = 100_000_000;
+    let fee = price / 10;
+
+    set_context(&owner(), 0, START);
+    assert!(contract.add_accepted_token(token()).unwrap());
+    set_context(&mentor(), 0, START);
+    assert!(contract
+        .set_course_token_price(0, token(), U128(price))
+        .unwrap());
+
+    // nothing carted, the whole transfer goes back
+    set_context(&token(), 0, START);
+    let msg = r#"{"course_ids":[0]}"#.to_string();
+    let unused = contract.ft_on_transfer(student(), U128(2 * price), msg.clone());
+    assert_eq!(unused_tokens(unused), 2 * price);
+
+    // courses not carted or not priced in the token are skipped
+    set_context(&student(), 0, START);
+    contract.save_course_to_cart(0).unwrap();
+    set_context(&token(), 0, START);
+    let msg = r#"{"course_ids":[0,7]}"#.to_string();
+    let unused = contract.ft_on_transfer(student(), U128(2 * price), msg);
+    assert_eq!(unused_tokens(unused), 2 * price - price - fee);
+
+    let enrollment = contract.get_enrollment(0, student()).unwrap();
+    assert_eq!(enrollment.status, EnrollmentStatus::Enrolled);
+    let payment = contract.get_enrollment_payment(enrollment.id).unwrap();
+    assert_eq!(payment.token_id, Some(token()));
+    assert_eq!(payment.price, U128(price));
+    assert_eq!(payment.fee, U128(fee));
+    assert_eq!(
+        contract
+            .get_mentor_token_earnings(mentor(), token())
+            .escrowed,
+        U128(price)
+    );
+}
+
+/// Enrolls `account_id` and completes the only lesson of course 0.
+fn complete_course(contract: &mut Contract, account_id: &AccountId, timestamp: u64) -> u64 {
+    let enrollment_id = enroll(contract, account_id, timestamp);
+    set_context(account_id, 0, timestamp);
+    assert!(contract.complete_lesson(0).unwrap());
+    enrollment_id
+}
+
+#[test]
+fn completing_a_course_mints_its_certificate() {
+    let mut contract = setup();
+    let enrollment_id = complete_course(&mut contract, &student(), START);
+    assert_eq!(
+        enrollment_status(&contract, enrollment_id),
+        EnrollmentStatus::Completed
+    );
+
+    let token = contract.nft_token("0".to_string()).unwrap();
+    assert_eq!(token.owner_id, student());
+    assert_eq!(token.metadata.issued_at, Some(START / 1_000_000));
+    assert_eq!(contract.nft_total_supply(), U128(1));
+    assert_eq!(contract.nft_supply_for_owner(student()), U128(1));
+    assert_eq!(
+        contract.nft_tokens_for_owner(student(), None, None)[0].token_id,
+        "0"
+    );
+    assert!(contract.nft_tokens(Some(U128(1)), None).is_empty());
+
+    // the certificate is linked from the profile
+    let student_profile = contract.get_user_by_id(student()).unwrap();
+    assert_eq!(student_profile.certifications.unwrap()[0].id, 0);
+    assert_eq!(contract.nft_supply_for_owner(other_student()), U128(0));
+}
+
+#[test]
+fn revoked_certificates_are_burned_but_still_verifiable() {
+    let mut contract = setup();
+    complete_course(&mut contract, &student(), START);
+    complete_course(&mut contract, &other_student(), START);
+    assert_eq!(contract.nft_total_supply(), U128(2));
+
+    // only admins revoke
+    set_context(&mentor(), 0, START + DAY);
+    assert!(matches!(
+        contract.revoke_certificate(0, "plagiarism".to_string()),
+        Err(ContractError::Unauthorized(_))
+    ));
+    set_context(&owner(), 0, START + DAY);
+    assert!(contract
+        .revoke_certificate(0, "plagiarism".to_string())
+        .unwrap());
+    assert!(matches!(
+        contract.revoke_certificate(0, "plagiarism".to_string()),
+        Err(ContractError::InvalidState(_))
+    ));
+
+    // the token is gone from every NFT view
+    assert!(contract.nft_token("0".to_string()).is_none());
+    assert_eq!(contract.nft_total_supply(), U128(1));
+    assert_eq!(contract.nft_supply_for_owner(student()), U128(0));
+    assert!(contract
+        .nft_tokens_for_owner(student(), None, None)
+        .is_empty());
+    let tokens = contract.nft_tokens(None, None);
+    assert_eq!(tokens.len(), 1);
+    assert_eq!(tokens[0].owner_id, other_student());
+
+    // and reported as revoked, not missing
+    let verification = contract.verify_certificate(0).unwrap();
+    assert_eq!(verification.status, CertificateStatus::Revoked);
+    assert_eq!(verification.revoked_at, Some(START + DAY));
+    let certificates = contract.get_certificates_of(student(), None, None);
+    assert_eq!(certificates.len(), 1);
+    assert_eq!(certificates[0].status, CertificateStatus::Revoked);
+    assert!(contract.verify_certificate(2).is_none());
+
+    let student_profile = contract.get_user_by_id(student()).unwrap();
+    assert!(student_profile.certifications.unwrap().is_empty());
+}
+
+fn review(
+    contract: &mut Contract,
+    account_id: &AccountId,
+    rating: u8,
+) -> Result<bool, ContractError> {
+    set_context(account_id, 0, START);
+    contract.submit_review(0, rating, "".to_string(), None)
+}
+
+#[test]
+fn reviews_keep_the_average_rating() {
+    let mut contract = setup();
+
+    // only enrolled students review, once, with 1 to 5 stars
+    assert!(matches!(
+        review(&mut contract, &student(), 5),
+        Err(ContractError::Unauthorized(_))
+    ));
+    enroll(&mut contract, &student(), START);
+    enroll(&mut contract, &other_student(), START);
+    assert!(matches!(
+        review(&mut contract, &student(), 6),
+        Err(ContractError::InvalidInput(_))
+    ));
+    assert!(review(&mut contract, &student(), 5).unwrap());
+    assert!(matches!(
+        review(&mut contract, &student(), 4),
+        Err(ContractError::AlreadyExists(_))
+    ));
+    assert!(review(&mut contract, &other_student(), 2).unwrap());
+
+    let course = contract.get_course_by_id(0).unwrap();
+    assert_eq!(course.rating_count, 2);
+    assert_eq!(course.average_rating, 3.5);
+
+    // only the mentor of the course replies
+    assert!(matches!(
+        contract.reply_to_review(0, "thanks".to_string()),
+        Err(ContractError::Unauthorized(_))
+    ));
+    set_context(&mentor(), 0, START);
+    assert!(contract.reply_to_review(0, "thanks".to_string()).unwrap());
+    assert_eq!(
+        contract.get_review_by_id(0).unwrap().mentor_reply,
+        Some("thanks".to_string())
+    );
+
+    // a hidden review leaves the listing and the average
+    set_context(&owner(), 0, START);
+    assert!(contract.hide_review(1).unwrap());
+    let course = contract.get_course_by_id(0).unwrap();
+    assert_eq!(course.rating_count, 1);
+    assert_eq!(course.average_rating, 5.0);
+    assert_eq!(contract.get_course_reviews(0, None, None).len(), 1);
+}
+
+#[test]
+fn release_escrow_settles_a_page_at_a_time() {
+    let mut contract = setup();
+    enroll(&mut contract, &student(), START);
+    enroll(&mut contract, &other_student(), START);
+
+    // nothing settles within the refund window
+    set_context(&owner(), 0, START + DAY);
+    assert_eq!(contract.release_escrow(mentor(), None, None), 0);
+
+    set_context(&owner(), 0, START + 8 * DAY);
+    assert_eq!(contract.release_escrow(mentor(), None, Some(1)), 1);
+    assert_eq!(
+        contract
+            .get_mentor_escrowed_payments(mentor(), None, None)
+            .len(),
+        1
+    );
+    assert_eq!(contract.get_treasury_balance(), U128(FEE));
+
+    // released sales leave the list, the next page starts at 0 again
+    assert_eq!(contract.release_escrow(mentor(), Some(1), None), 0);
+    assert_eq!(contract.release_escrow(mentor(), None, Some(1)), 1);
+    assert!(contract
+        .get_mentor_escrowed_payments(mentor(), None, None)
+        .is_empty());
+    assert_eq!(contract.get_treasury_balance(), U128(2 * FEE));
+}
+
+/// 20% up front, the rest once the whole course is done or after 30 days.
+fn set_escrow_policy(contract: &mut Contract) {
+    set_context(&owner(), 0, START);
+    assert!(contract
+        .set_course_escrow_policy(0, 20, 100, 30 * 24 * 60 * 60)
+        .unwrap());
+}
+
+#[test]
+fn upfront_share_is_released_at_enrollment_and_not_refunded() {
+    let mut contract = setup();
+    set_escrow_policy(&mut contract);
+    let upfront = PRICE / 5;
+
+    let enrollment_id = enroll(&mut contract, &student(), START);
+    let payment = contract.get_enrollment_payment(enrollment_id).unwrap();
+    assert_eq!(payment.released, U128(upfront));
+    assert_eq!(payment.escrowed, U128(PRICE - upfront));
+    let earnings = contract.get_mentor_earnings(mentor());
+    assert_eq!(earnings.pending, U128(upfront));
+    assert_eq!(earnings.escrowed, U128(PRICE - upfront));
+
+    // a refund only pays back what is still escrowed and the fee
+    set_context(&student(), 0, START + DAY);
+    assert!(contract.request_refund(0).unwrap());
+    set_callback_context(START + DAY, PromiseResult::Successful(vec![]));
+    assert!(contract.on_refund_resolved(enrollment_id));
+    let earnings = contract.get_mentor_earnings(mentor());
+    assert_eq!(earnings.lifetime, U128(upfront));
+    assert_eq!(earnings.pending, U128(upfront));
+    assert_eq!(earnings.escrowed, U128(0));
+}
+
+#[test]
+fn escrow_is_released_at_the_milestone() {
+    let mut contract = setup();
+    set_escrow_policy(&mut contract);
+    let enrollment_id = enroll(&mut contract, &student(), START);
+
+    // settled once the refund window closes, the rest waits for the milestone
+    set_context(&mentor(), 0, START + 8 * DAY);
+    assert_eq!(contract.release_escrow(mentor(), None, None), 1);
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Settled
+    );
+    assert_eq!(contract.release_escrow(mentor(), None, None), 0);
+
+    set_context(&student(), 0, START + 8 * DAY);
+    assert!(contract.complete_lesson(0).unwrap());
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Released
+    );
+    let earnings = contract.get_mentor_earnings(mentor());
+    assert_eq!(earnings.pending, U128(PRICE));
+    assert_eq!(earnings.escrowed, U128(0));
+    assert!(contract
+        .get_mentor_escrowed_payments(mentor(), None, None)
+        .is_empty());
+}
+
+#[test]
+fn escrow_is_released_after_the_timeout() {
+    let mut contract = setup();
+    set_escrow_policy(&mut contract);
+    let enrollment_id = enroll(&mut contract, &student(), START);
+
+    set_context(&mentor(), 0, START + 29 * DAY);
+    assert_eq!(contract.release_escrow(mentor(), None, None), 1);
+    assert_eq!(
+        payment_status(&contract, enrollment_id),
+        PaymentStatus::Settled
+    );
+
+    set_context(&mentor(), 0, START + 30 * DAY);
+    assert_eq!(contract.release_escrow(mentor(), None, None), 1);
+    let payment = contract.get_enrollment_payment(enrollment_id).unwrap();
+    assert_eq!(payment.status, PaymentStatus::Released);
+    assert_eq!(payment.released, U128(PRICE));
+    assert_eq!(payment.escrowed, U128(0));
+}
+
+#[test]
+fn progress_weighs_lessons_and_quizzes() {
+    let mut contract = setup();
+    add_quizz(&mut contract);
+    let enrollment_id = enroll(&mut contract, &student(), START);
+
+    // one of the lesson and the quizz of the module is done
+    set_context(&student(), 0, START);
+    assert!(contract.complete_lesson(0).unwrap());
+    let report = contract.get_student_progress_report(student(), 0).unwrap();
+    assert_eq!(report.progress, 50);
+    assert_eq!(report.completed_lessons, 1);
+    assert_eq!(report.total_quizzes, 1);
+    assert_eq!(report.modules[0].progress, 50);
+    assert!(!report.modules[0].quizz_passed);
+    assert_eq!(
+        contract.enrollments.get(&enrollment_id).unwrap().progress,
+        50
+    );
+    assert_eq!(
+        contract.get_module_progress(0, student()).unwrap().progress,
+        50
+    );
+
+    // lessons are completed once
+    assert!(matches!(
+        contract.complete_lesson(0),
+        Err(ContractError::InvalidState(_))
+    ));
+
+    assert!(contract.submit_quizz(0, vec![0, 0]).unwrap().passed);
+    let report = contract.get_student_progress_report(student(), 0).unwrap();
+    assert_eq!(report.progress, 100);
+    assert_eq!(report.enrollment_status, EnrollmentStatus::Completed);
+    assert_eq!(
+        contract.get_module_progress(0, student()).unwrap().status,
+        ProgressStatus::Completed
+    );
+}
+
+#[test]
+fn content_added_after_enrolling_gets_progress_rows() {
+    let mut contract = setup();
+    enroll(&mut contract, &student(), START);
+
+    // the mentor adds a lesson after the student enrolled
+    set_context(&mentor(), 0, START);
+    assert!(contract
+        .create_lesson(
+            0,
+            "Borrowing".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            2,
+            false,
+            None,
+        )
+        .unwrap());
+    assert!(contract.get_lesson_progress(1, student()).is_none());
+
+    set_context(&student(), 0, START);
+    assert!(contract.complete_lesson(1).unwrap());
+    assert_eq!(
+        contract.get_lesson_progress(1, student()).unwrap().status,
+        ProgressStatus::Completed
+    );
+    let report = contract.get_student_progress_report(student(), 0).unwrap();
+    assert_eq!(report.total_lessons, 2);
+    assert_eq!(report.progress, 50);
+    assert_eq!(report.enrollment_status, EnrollmentStatus::Enrolled);
+
+    // students not enrolled get no rows
+    set_context(&other_student(), 0, START);
+    assert!(matches!(
+        contract.complete_lesson(1),
+        Err(ContractError::InvalidState(_))
+    ));
+    assert!(contract.get_lesson_progress(1, other_student()).is_none());
+}
+
+/// Adds draft courses 1 to `count` of `mentor`, course `i` costs `i` NEAR.
+fn add_courses(contract: &mut Contract, count: u64) {
+    set_context(&mentor(), 0, START);
+    for i in 1..=count {
+        contract
+            .create_course(
+                format!("Course {}", i),
+                "".to_string(),
+                "beginner".to_string(),
+                "1 month".to_string(),
+                if i % 2 == 0 { "design" } else { "programming" }.to_string(),
+                vec![],
+                vec![],
+                "".to_string(),
+                false,
+                U128(i as u128 * ONE_NEAR),
+                None,
+            )
+            .unwrap();
+    }
+}
+
+fn course_ids(page: &CourseSearchPage) -> Vec<u64> {
+    page.courses.iter().map(|course| course.id).collect()
+}
+
+#[test]
+fn search_pages_follow_course_ids() {
+    let mut contract = setup();
+    add_courses(&mut contract, 4);
+
+    let page = contract.search_courses(CourseFilter::default(), None, None, Some(2));
+    assert_eq!(course_ids(&page), vec![0, 1]);
+    assert_eq!(page.next_index, Some(2));
+    let page = contract.search_courses(CourseFilter::default(), None, Some(2), Some(2));
+    assert_eq!(course_ids(&page), vec![2, 3]);
+    let page = contract.search_courses(CourseFilter::default(), None, Some(4), Some(2));
+    assert_eq!(course_ids(&page), vec![4]);
+    assert_eq!(page.next_index, None);
+
+    // the sort only orders the page
+    let page = contract.search_courses(
+        CourseFilter::default(),
+        Some(CourseSort::PriceAsc),
+        Some(1),
+        Some(3),
+    );
+    assert_eq!(course_ids(&page), vec![1, 2, 3]);
+    let page = contract.search_courses(
+        CourseFilter::default(),
+        Some(CourseSort::PriceDesc),
+        Some(1),
+        Some(3),
+    );
+    assert_eq!(course_ids(&page), vec![3, 2, 1]);
+    assert_eq!(page.next_index, Some(4));
+
+    // filters skip what does not match, the cursor stays a course id
+    let filter = CourseFilter {
+        category: Some("design".to_string()),
+        mentor_id: Some(mentor()),
+        ..Default::default()
+    };
+    let page = contract.search_courses(filter.clone(), None, None, Some(1));
+    assert_eq!(course_ids(&page), vec![2]);
+    assert_eq!(page.next_index, Some(3));
+    let page = contract.search_courses(filter, None, Some(3), None);
+    assert_eq!(course_ids(&page), vec![4]);
+    assert_eq!(page.next_index, None);
+
+    // deleted courses leave a gap in the ids
+    set_context(&mentor(), 0, START);
+    contract.delete_course(2).unwrap();
+    let page = contract.search_courses(CourseFilter::default(), None, Some(1), Some(2));
+    assert_eq!(course_ids(&page), vec![1, 3]);
+}
+
+#[test]
+fn list_getters_are_paged() {
+    let mut contract = setup();
+    add_courses(&mut contract, 11);
+
+    assert_eq!(contract.get_full_courses(None, Some(50)).len(), 10);
+    assert_eq!(contract.get_full_courses(Some(10), None).len(), 2);
+    assert_eq!(
+        contract
+            .get_user_created_full_courses(mentor(), Some(10), None)
+            .len(),
+        2
+    );
+    let courses = contract.get_mentor_created_courses(mentor(), Some(1), Some(2));
+    assert_eq!(
+        courses.iter().map(|course| course.id).collect::<Vec<u64>>(),
+        vec![1, 2]
+    );
+
+    enroll(&mut contract, &student(), START);
+    enroll(&mut contract, &other_student(), START);
+    assert_eq!(
+        contract.get_user_enrollments(student(), None, None).len(),
+        1
+    );
+    assert!(contract
+        .get_user_enrollments(student(), Some(1), None)
+        .is_empty());
+    assert_eq!(
+        contract
+            .get_user_enrolled_full_courses(student(), None, None)
+            .len(),
+        1
+    );
+    let payments = contract.get_mentor_escrowed_payments(mentor(), Some(1), Some(5));
+    assert_eq!(payments.len(), 1);
+    assert_eq!(payments[0].student_id, other_student());
+
+    set_context(&owner(), 0, START);
+    for account_id in [mentor(), student(), other_student()] {
+        contract.grant_role(account_id, Role::Moderator).unwrap();
+    }
+    assert_eq!(
+        contract
+            .get_role_members(Role::Moderator, None, Some(2))
+            .len(),
+        2
+    );
+    assert_eq!(
+        contract
+            .get_role_members(Role::Moderator, Some(2), None)
+            .len(),
+        1
+    );
+    assert_eq!(
+        contract.get_role_members(Role::Owner, None, None),
+        vec![owner()]
+    );
+}
+
+#[test]
+fn courses_with_students_are_archived_not_deleted() {
+    let mut contract = setup();
+    enroll(&mut contract, &student(), START);
+
+    set_context(&mentor(), 0, START + DAY);
+    assert!(matches!(
+        contract.delete_course(0),
+        Err(ContractError::InvalidState(_))
+    ));
+    assert!(matches!(
+        contract.delete_module(0),
+        Err(ContractError::InvalidState(_))
+    ));
+    assert!(matches!(
+        contract.delete_lesson(0),
+        Err(ContractError::InvalidState(_))
+    ));
+    assert!(contract.get_lesson_by_id(0).is_some());
+
+    contract.archive_course(0).unwrap();
+    let course = contract.get_course_by_id(0).unwrap();
+    assert_eq!(course.status, CourseStatus::Archived);
+    assert_eq!(course.updated_at, START + DAY);
+}
+
+#[test]
+fn is_permutation_needs_every_id_once() {
+    assert!(crate::is_permutation(&[1, 2, 3], &[3, 1, 2]));
+    assert!(crate::is_permutation(&[], &[]));
+    assert!(!crate::is_permutation(&[1, 2, 3], &[1, 2]));
+    assert!(!crate::is_permutation(&[1, 2, 3], &[1, 2, 2]));
+    assert!(!crate::is_permutation(&[1, 2], &[1, 2, 3]));
+    assert!(!crate::is_permutation(&[1, 2], &[1, 4]));
+}
+
+#[test]
+fn reorder_rewrites_the_content_order() {
+    let mut contract = setup();
+    set_context(&mentor(), 0, START);
+    assert!(contract
+        .create_lesson(
+            0,
+            "Borrowing".to_string(),
+            "".to_string(),
+            "".to_string(),
+            "".to_string(),
+            2,
+            false,
+            None,
+        )
+        .unwrap());
+    assert!(create_module(&mut contract, 0).unwrap());
+
+    for lessons_ids in [vec![0], vec![0, 0], vec![0, 1, 2]] {
+        assert!(matches!(
+            contract.reorder_module_lessons(0, lessons_ids),
+            Err(ContractError::InvalidInput(_))
+        ));
+    }
+
+    set_context(&mentor(), 0, START + DAY);
+    contract.reorder_module_lessons(0, vec![1, 0]).unwrap();
+    let module = contract.get_full_module_by_id(0).unwrap();
+    let lessons_ids: Vec<u64> = module.lessons.iter().map(|lesson| lesson.id).collect();
+    assert_eq!(lessons_ids, vec![1, 0]);
+    assert_eq!(contract.get_lesson_by_id(1).unwrap().order, 1);
+    assert_eq!(
+        contract.get_module_by_id(0).unwrap().updated_at,
+        START + DAY
+    );
+
+    assert!(matches!(
+        contract.reorder_course_modules(0, vec![1]),
+        Err(ContractError::InvalidInput(_))
+    ));
+    contract.reorder_course_modules(0, vec![1, 0]).unwrap();
+    let course = contract.get_full_course(0).unwrap();
+    let modules_ids: Vec<u64> = course.modules.iter().map(|module| module.id).collect();
+    assert_eq!(modules_ids, vec![1, 0]);
+
+    // only the course mentor reorders
+    set_context(&student(), 0, START + DAY);
+    assert!(matches!(
+        contract.reorder_course_modules(0, vec![0, 1]),
+        Err(ContractError::Unauthorized(_))
+    ));
+}
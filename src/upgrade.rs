@@ -0,0 +1,136 @@
+use crate::errors::ContractError;
+use crate::models::*;
+use crate::{Contract, ContractExt};
+use near_sdk::env::log_str;
+use near_sdk::json_types::{Base58CryptoHash, Base64VecU8};
+use near_sdk::{env, near_bindgen, FunctionError, Gas, Promise};
+
+/// Storage key the staged binary is written under. It lives outside the root
+/// struct so the code is not read and written back on every call.
+const STAGED_CODE_KEY: &[u8] = b"UPGRADE_CODE";
+
+/// Gas kept back for the deploy action itself; whatever is left of the
+/// prepaid gas goes to the `migrate` call.
+const GAS_FOR_DEPLOY: Gas = Gas(20_000_000_000_000);
+
+#[near_bindgen]
+impl Contract {
+    pub fn stage_upgrade(&mut self, code: Base64VecU8) -> Base58CryptoHash {
+        let owner_id = self.assert_role(Role::Owner, "Only the owner can stage an upgrade");
+        let initial_storage = env::storage_usage();
+
+        let code: Vec<u8> = code.into();
+        if code.is_empty() {
+            ContractError::InvalidInput("The staged code is empty".to_string()).panic()
+        }
+
+        // a new stage replaces any previous one
+        let code_hash: Base58CryptoHash = env::sha256_array(&code).into();
+        env::storage_write(STAGED_CODE_KEY, &code);
+        self.pending_upgrade = Some(PendingUpgrade {
+            code_hash,
+            code_size: code.len() as u64,
+            staged_by: owner_id.clone(),
+            staged_at: env::block_timestamp(),
+        });
+
+        log_str(&format!(
+            "Upgrade staged: {} bytes, hash {}",
+            code.len(),
+            String::from(&code_hash)
+        ));
+
+        // charge the caller for the storage used
+        self.charge_storage(&owner_id, initial_storage);
+
+        code_hash
+    }
+
+    pub fn get_pending_upgrade(&self) -> Option<PendingUpgrade> {
+        self.pending_upgrade.clone()
+    }
+
+    #[handle_result]
+    pub fn cancel_upgrade(&mut self) -> Result<bool, ContractError> {
+        let owner_id = self.require_role(Role::Owner, "Only the owner can cancel an upgrade")?;
+        let initial_storage = env::storage_usage();
+
+        if self.pending_upgrade.is_none() {
+            return Err(ContractError::NotFound(
+                "There is no pending upgrade".to_string(),
+            ));
+        }
+
+        env::storage_remove(STAGED_CODE_KEY);
+        self.pending_upgrade = None;
+
+        log_str("Pending upgrade cancelled");
+
+        // release the storage freed by the caller
+        self.charge_storage(&owner_id, initial_storage);
+
+        Ok(true)
+    }
+
+    /// Deploys the staged binary to this account and chains a call to
+    /// `migrate`. `code_hash` must match the staged code so the owner confirms
+    /// exactly what is being deployed.
+    pub fn deploy_upgrade(&mut self, code_hash: Base58CryptoHash) -> Promise {
+        let owner_id = self.assert_role(Role::Owner, "Only the owner can deploy an upgrade");
+        let initial_storage = env::storage_usage();
+
+        let pending_upgrade = self.pending_upgrade.clone();
+        if pending_upgrade.is_none() {
+            ContractError::NotFound("There is no pending upgrade".to_string()).panic()
+        }
+        let pending_upgrade = pending_upgrade.unwrap();
+
+        if pending_upgrade.code_hash != code_hash {
+            ContractError::InvalidInput(
+                "The code hash does not match the staged upgrade".to_string(),
+            )
+            .panic()
+        }
+
+        let code = env::storage_read(STAGED_CODE_KEY);
+        if code.is_none() {
+            ContractError::InvalidState("The staged code is missing".to_string()).panic()
+        }
+        let code = code.unwrap();
+
+        // the stored bytes must still be the ones that were staged
+        let stored_hash: Base58CryptoHash = env::sha256_array(&code).into();
+        if stored_hash != pending_upgrade.code_hash {
+            ContractError::InvalidState(
+                "The staged code does not match its recorded hash".to_string(),
+            )
+            .panic()
+        }
+
+        let remaining_gas = env::prepaid_gas() - env::used_gas();
+        if remaining_gas <= GAS_FOR_DEPLOY {
+            ContractError::InvalidInput("Not enough gas attached to deploy and migrate".to_string())
+                .panic()
+        }
+
+        env::storage_remove(STAGED_CODE_KEY);
+        self.pending_upgrade = None;
+
+        log_str(&format!(
+            "Deploying upgrade with hash {}",
+            String::from(&code_hash)
+        ));
+
+        // release the storage freed by the caller
+        self.charge_storage(&owner_id, initial_storage);
+
+        Promise::new(env::current_account_id())
+            .deploy_contract(code)
+            .function_call(
+                "migrate".to_string(),
+                vec![],
+                0,
+                remaining_gas - GAS_FOR_DEPLOY,
+            )
+    }
+}
@@ -1,4 +1,7 @@
-use crate::{Contract, ContractExt, Course};
+use crate::{
+    Contract, ContractExt, Course, Enrollment, EnrollmentStatus, ProgressStatus, QuizzStatus, Role,
+    User,
+};
 use near_sdk::{env, log, near_bindgen, AccountId};
 
 #[near_bindgen]
@@ -13,31 +16,17 @@ impl Contract {
     }
 
     pub fn user_exists_by_account_id(&self, account_id: AccountId) -> bool {
-        for user in self.users.iter() {
-            if user.account_id == account_id {
-                return true;
-            }
-        }
-        false
+        self.users.contains_key(&account_id)
     }
 
     pub fn user_exists(&self, account_id: AccountId, username: String, email: String) -> bool {
-        for user in self.users.iter() {
-            if user.account_id == account_id || user.username == username || user.email == email {
-                return true;
-            }
-        }
-        false
+        self.users.contains_key(&account_id)
+            || self.usernames.contains_key(&username)
+            || self.emails.contains_key(&email)
     }
 
     pub fn is_admin(&self, account_id: AccountId) -> bool {
-        let user = self.get_user_by_id(account_id);
-        if let Some(user) = user {
-            if user.role == "admin" {
-                return true;
-            }
-        }
-        false
+        self.has_role(account_id, Role::Admin)
     }
 
     pub fn is_course_mentor(&self, course_id: u64, account_id: AccountId) -> bool {
@@ -51,7 +40,7 @@ impl Contract {
     pub fn is_student_course_carted(&self, course_id: u64, account_id: AccountId) -> bool {
         let enrollment = self.get_enrollment(course_id, account_id);
         if let Some(enrollment) = enrollment {
-            if enrollment.status == "carted" {
+            if enrollment.status == EnrollmentStatus::Carted {
                 return true;
             }
         }
@@ -61,7 +50,7 @@ impl Contract {
     pub fn is_student_course_enrolled(&self, course_id: u64, account_id: AccountId) -> bool {
         let enrollment = self.get_enrollment(course_id, account_id);
         if let Some(enrollment) = enrollment {
-            if enrollment.status == "enrolled" {
+            if enrollment.status == EnrollmentStatus::Enrolled {
                 return true;
             }
         }
@@ -71,29 +60,27 @@ impl Contract {
     pub fn is_student_course_completed(&self, course_id: u64, account_id: AccountId) -> bool {
         let enrollment = self.get_enrollment(course_id, account_id);
         if let Some(enrollment) = enrollment {
-            if enrollment.status == "completed" {
+            if enrollment.status == EnrollmentStatus::Completed {
                 return true;
             }
         }
         false
     }
 
+    /// Checks the course enrollment rather than the progress row, which
+    /// does not exist yet for modules added after the student enrolled.
     pub fn is_student_module_enrolled(&self, module_id: u64, account_id: AccountId) -> bool {
-        let module_progress = self.get_module_progress(module_id, account_id);
-        if let Some(module_progress) = module_progress {
-            if module_progress.is_enrolled == true {
-                return true;
-            }
+        let module = self.get_module_by_id(module_id);
+        if let Some(module) = module {
+            return self.internal_is_course_enrolled(module.course_id, &account_id);
         }
         false
     }
 
     pub fn is_student_lesson_enrolled(&self, lesson_id: u64, account_id: AccountId) -> bool {
-        let lesson_progress = self.get_lesson_progress(lesson_id, account_id);
-        if let Some(lesson_progress) = lesson_progress {
-            if lesson_progress.is_enrolled == true {
-                return true;
-            }
+        let lesson = self.get_lesson_by_id(lesson_id);
+        if let Some(lesson) = lesson {
+            return self.is_student_module_enrolled(lesson.module_id, account_id);
         }
         false
     }
@@ -101,7 +88,7 @@ impl Contract {
     pub fn is_student_lesson_completed(&self, lesson_id: u64, account_id: AccountId) -> bool {
         let lesson_progress = self.get_lesson_progress(lesson_id, account_id);
         if let Some(lesson_progress) = lesson_progress {
-            if lesson_progress.status == "completed" {
+            if lesson_progress.status == ProgressStatus::Completed {
                 return true;
             }
         }
@@ -115,7 +102,19 @@ impl Contract {
             for lesson_id in lessons.iter() {
                 let lesson_progress = self.get_lesson_progress(*lesson_id, account_id.clone());
                 if let Some(lesson_progress) = lesson_progress {
-                    if lesson_progress.status != "completed" {
+                    if lesson_progress.status != ProgressStatus::Completed {
+                        return false;
+                    }
+                } else {
+                    return false;
+                }
+            }
+
+            // the module quizz must be passed too
+            if let Some(quizz_id) = module.quizz_id {
+                let quizz_progress = self.get_quizz_progress(quizz_id, account_id);
+                if let Some(quizz_progress) = quizz_progress {
+                    if quizz_progress.status != QuizzStatus::Completed {
                         return false;
                     }
                 } else {
@@ -127,15 +126,36 @@ impl Contract {
         false
     }
 
+    /// Percentage of the course lessons the student has completed.
+    pub fn calculate_lessons_completed_percentage(
+        &self,
+        course_id: u64,
+        account_id: AccountId,
+    ) -> u16 {
+        let mut total_lessons: u64 = 0;
+        let mut completed_lessons: u64 = 0;
+        for module in self.get_course_modules(course_id).iter() {
+            for lesson_id in module.lessons_ids.iter() {
+                total_lessons += 1;
+                if self.is_student_lesson_completed(*lesson_id, account_id.clone()) {
+                    completed_lessons += 1;
+                }
+            }
+        }
+        if total_lessons == 0 {
+            return 0;
+        }
+        (completed_lessons * 100 / total_lessons) as u16
+    }
+
     pub fn calculate_total_courses_price_with_fee(&self, courses: Vec<Course>) -> u128 {
         let mut total_price: u128 = 0;
-        let fee_percentage: u128 = 10;
 
         for course in courses.iter() {
             // add teh fee to each course
             let course_price = course.price;
             log!("Course Price: {}", course_price);
-            let course_fee = course_price * fee_percentage / 100;
+            let course_fee = self.calculate_course_fee(course_price);
             log!("Course Fee: {}", course_fee);
             let course_total_price = course_price + course_fee;
             log!("Course Total Price: {}", course_total_price);
@@ -144,17 +164,20 @@ impl Contract {
         total_price
     }
 
-   
-
-    /* pub fn calculate_module_creation_progress(&self, module_id: u64) -> u64 {
-        let module = self.get_module_by_id(module_id).unwrap();
-        let lessons = module.lessons_ids;
+    /// Percentage of the module lessons that have an article or a video.
+    pub fn calculate_module_creation_progress(&self, module_id: u64) -> u64 {
+        let module = self.get_module_by_id(module_id);
+        if module.is_none() {
+            return 0;
+        }
+        let lessons = module.unwrap().lessons_ids;
         let mut completed_lessons = 0;
         for lesson_id in lessons.iter() {
             // check if the lesson has artcile or video url content
-            let lesson = self.get_lesson_by_id(*lesson_id).unwrap();
-            if !lesson.article.is_empty() || !lesson.video_url.is_empty() {
-                completed_lessons += 1;
+            if let Some(lesson) = self.get_lesson_by_id(*lesson_id) {
+                if !lesson.article.is_empty() || !lesson.video_url.is_empty() {
+                    completed_lessons += 1;
+                }
             }
         }
 
@@ -162,15 +185,15 @@ impl Contract {
         if total_lessons == 0 {
             return 0;
         }
-        let progress = (completed_lessons * 100) / total_lessons;
-        progress
+        (completed_lessons * 100) / total_lessons
     }
 
+    /// Percentage of the course modules whose lessons all have content.
     pub fn calculate_course_creation_progress(&self, course: Course) -> u64 {
         let modules = course.modules_ids;
         let total_modules = modules.len() as u64;
         if total_modules == 0 {
-            return 0; 
+            return 0;
         }
         let mut completed_modules = 0;
         for module_id in modules.iter() {
@@ -179,7 +202,114 @@ impl Contract {
                 completed_modules += 1;
             }
         }
-        let progress = (completed_modules * 100) / total_modules;
-        progress
-    } */
+        (completed_modules * 100) / total_modules
+    }
+}
+
+impl Contract {
+    /// Stores `user` and keeps the username and email indexes in sync.
+    pub(crate) fn internal_save_user(&mut self, user: User) {
+        if let Some(old_user) = self.users.get(&user.account_id).cloned() {
+            if old_user.username != user.username {
+                self.usernames.remove(&old_user.username);
+            }
+            if old_user.email != user.email {
+                self.emails.remove(&old_user.email);
+            }
+        }
+
+        self.usernames
+            .insert(user.username.clone(), user.account_id.clone());
+        self.emails
+            .insert(user.email.clone(), user.account_id.clone());
+        self.users.insert(user.account_id.clone(), user);
+    }
+
+    /// Whether `username` or `email` already belong to an account other than `account_id`.
+    pub(crate) fn is_username_or_email_taken(
+        &self,
+        account_id: &AccountId,
+        username: &String,
+        email: &String,
+    ) -> bool {
+        let username_owner = self.usernames.get(username);
+        let email_owner = self.emails.get(email);
+        username_owner.is_some_and(|owner| owner != account_id)
+            || email_owner.is_some_and(|owner| owner != account_id)
+    }
+
+    /// Stores a new enrollment and adds it to the course and student indexes.
+    pub(crate) fn internal_add_enrollment(&mut self, enrollment: Enrollment) {
+        self.enrollment_ids.insert(
+            (enrollment.course_id, enrollment.student_id.clone()),
+            enrollment.id,
+        );
+        self.student_enrollments
+            .entry(enrollment.student_id.clone())
+            .or_default()
+            .push(enrollment.id);
+        self.course_enrollments
+            .entry(enrollment.course_id)
+            .or_default()
+            .push(enrollment.id);
+        self.enrollments.insert(enrollment.id, enrollment);
+    }
+
+    /// Removes an enrollment and its index entries, dropping the indexes
+    /// left empty so the student gets all of the bytes back.
+    pub(crate) fn internal_remove_enrollment(&mut self, enrollment: &Enrollment) {
+        self.enrollment_ids
+            .remove(&(enrollment.course_id, enrollment.student_id.clone()));
+        if let Some(enrollment_ids) = self.student_enrollments.get_mut(&enrollment.student_id) {
+            enrollment_ids.retain(|id| *id != enrollment.id);
+            if enrollment_ids.is_empty() {
+                self.student_enrollments.remove(&enrollment.student_id);
+            }
+        }
+        if let Some(enrollment_ids) = self.course_enrollments.get_mut(&enrollment.course_id) {
+            enrollment_ids.retain(|id| *id != enrollment.id);
+            if enrollment_ids.is_empty() {
+                self.course_enrollments.remove(&enrollment.course_id);
+            }
+        }
+        self.enrollments.remove(&enrollment.id);
+    }
+
+    /// Every enrollment record of a student, whatever its status.
+    pub(crate) fn internal_student_enrollments(&self, account_id: &AccountId) -> Vec<Enrollment> {
+        let mut enrollment_list: Vec<Enrollment> = vec![];
+        if let Some(enrollment_ids) = self.student_enrollments.get(account_id) {
+            for enrollment_id in enrollment_ids.iter() {
+                if let Some(enrollment) = self.enrollments.get(enrollment_id) {
+                    enrollment_list.push(enrollment.clone());
+                }
+            }
+        }
+        enrollment_list
+    }
+
+    /// Every enrollment record of a course, whatever its status.
+    pub(crate) fn internal_course_enrollments(&self, course_id: u64) -> Vec<Enrollment> {
+        let mut enrollment_list: Vec<Enrollment> = vec![];
+        if let Some(enrollment_ids) = self.course_enrollments.get(&course_id) {
+            for enrollment_id in enrollment_ids.iter() {
+                if let Some(enrollment) = self.enrollments.get(enrollment_id) {
+                    enrollment_list.push(enrollment.clone());
+                }
+            }
+        }
+        enrollment_list
+    }
+
+    /// Whether students are enrolled in or have graduated from the course.
+    /// Such courses keep their content, they can only be archived.
+    pub(crate) fn has_active_enrollments(&self, course_id: u64) -> bool {
+        self.internal_course_enrollments(course_id)
+            .iter()
+            .any(|enrollment| {
+                enrollment.status == EnrollmentStatus::Enrolled
+                    || enrollment.status == EnrollmentStatus::Completed
+                    || enrollment.status == EnrollmentStatus::Refunding
+            })
+    }
 }